@@ -0,0 +1 @@
+DROP TABLE searchindex;
\ No newline at end of file
@@ -0,0 +1,31 @@
+CREATE VIRTUAL TABLE searchindex USING fts5
+(
+    kind UNINDEXED,
+    target UNINDEXED,
+    name,
+    body,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO searchindex
+    (kind, target, name, body)
+SELECT 'labelset', uuid, name, ''
+FROM labelsets;
+
+INSERT INTO searchindex
+    (kind, target, name, body)
+SELECT 'label', labelsets.uuid, labels.name, labelsets.name
+FROM labels
+JOIN labelsets ON labels.labelset = labelsets.id;
+
+INSERT INTO searchindex
+    (kind, target, name, body)
+SELECT 'quiz', uuid, name, COALESCE(
+    (SELECT group_concat(textprompt, ' ') FROM questions WHERE questions.quiz = quizzes.id),
+    '')
+FROM quizzes;
+
+INSERT INTO searchindex
+    (kind, target, name, body)
+SELECT 'model', CAST(id AS TEXT), filename, COALESCE(category, '')
+FROM models;
@@ -0,0 +1,34 @@
+ALTER TABLE labelsets RENAME TO templabelsets;
+
+CREATE TABLE labelsets
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    uuid TEXT UNIQUE NOT NULL,
+    name TEXT NOT NULL,
+    model INTEGER NOT NULL
+);
+
+INSERT INTO labelsets
+    (id, uuid, name, model)
+SELECT id, uuid, name, model
+FROM templabelsets;
+
+DROP TABLE templabelsets;
+
+ALTER TABLE quizzes RENAME TO tempquizzes;
+
+CREATE TABLE quizzes
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    uuid TEXT UNIQUE NOT NULL,
+    name TEXT NOT NULL,
+    labelset INTEGER NOT NULL,
+    shuffle SMALLINT NOT NULL
+);
+
+INSERT INTO quizzes
+    (id, uuid, name, labelset, shuffle)
+SELECT id, uuid, name, labelset, shuffle
+FROM tempquizzes;
+
+DROP TABLE tempquizzes;
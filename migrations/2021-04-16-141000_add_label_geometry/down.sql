@@ -0,0 +1,17 @@
+ALTER TABLE labels RENAME TO templabels;
+
+CREATE TABLE labels
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    labelset INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    colour TEXT NOT NULL,
+    vertices BLOB NOT NULL
+);
+
+INSERT INTO labels
+    (id, labelset, name, colour, vertices)
+SELECT id, labelset, name, colour, vertices
+FROM templabels;
+
+DROP TABLE templabels;
@@ -0,0 +1,28 @@
+ALTER TABLE labels RENAME TO templabels;
+
+CREATE TABLE labels
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    labelset INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    colour TEXT NOT NULL,
+    vertices BLOB NOT NULL,
+    vertexcount INTEGER DEFAULT NULL,
+    area DOUBLE DEFAULT NULL,
+    centroidx DOUBLE DEFAULT NULL,
+    centroidy DOUBLE DEFAULT NULL,
+    centroidz DOUBLE DEFAULT NULL,
+    minx DOUBLE DEFAULT NULL,
+    miny DOUBLE DEFAULT NULL,
+    minz DOUBLE DEFAULT NULL,
+    maxx DOUBLE DEFAULT NULL,
+    maxy DOUBLE DEFAULT NULL,
+    maxz DOUBLE DEFAULT NULL
+);
+
+INSERT INTO labels
+    (id, labelset, name, colour, vertices)
+SELECT id, labelset, name, colour, vertices
+FROM templabels;
+
+DROP TABLE templabels;
@@ -0,0 +1 @@
+DROP TABLE questionoptions;
\ No newline at end of file
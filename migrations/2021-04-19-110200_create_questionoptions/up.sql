@@ -0,0 +1,8 @@
+CREATE TABLE questionoptions
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    question INTEGER NOT NULL,
+    textoption TEXT,
+    label INTEGER,
+    correct SMALLINT NOT NULL
+);
@@ -0,0 +1,19 @@
+ALTER TABLE questions RENAME TO tempquestions;
+
+CREATE TABLE questions
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    quiz INTEGER NOT NULL,
+    questiontype SMALLINT NOT NULL,
+    textprompt TEXT NOT NULL,
+    textanswer TEXT,
+    label INTEGER,
+    showregions SMALLINT NOT NULL
+);
+
+INSERT INTO questions
+    (id, quiz, questiontype, textprompt, textanswer, label, showregions)
+SELECT id, quiz, questiontype, textprompt, textanswer, label, showregions
+FROM tempquestions;
+
+DROP TABLE tempquestions;
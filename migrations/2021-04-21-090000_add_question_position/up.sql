@@ -0,0 +1,21 @@
+ALTER TABLE questions RENAME TO tempquestions;
+
+CREATE TABLE questions
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    quiz INTEGER NOT NULL,
+    questiontype SMALLINT NOT NULL,
+    textprompt TEXT NOT NULL,
+    textanswer TEXT,
+    label INTEGER,
+    showregions SMALLINT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0
+);
+
+-- Questions used to come back in insertion order, so keep that as their position.
+INSERT INTO questions
+    (id, quiz, questiontype, textprompt, textanswer, label, showregions, position)
+SELECT id, quiz, questiontype, textprompt, textanswer, label, showregions, id
+FROM tempquestions;
+
+DROP TABLE tempquestions;
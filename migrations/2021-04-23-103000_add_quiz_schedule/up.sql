@@ -0,0 +1,21 @@
+ALTER TABLE quizzes RENAME TO tempquizzes;
+
+CREATE TABLE quizzes
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    uuid TEXT UNIQUE NOT NULL,
+    name TEXT NOT NULL,
+    labelset INTEGER NOT NULL,
+    shuffle SMALLINT NOT NULL,
+    owner INTEGER DEFAULT NULL,
+    opensat BIGINT DEFAULT NULL,
+    closesat BIGINT DEFAULT NULL,
+    timelimit INTEGER DEFAULT NULL
+);
+
+INSERT INTO quizzes
+    (id, uuid, name, labelset, shuffle, owner)
+SELECT id, uuid, name, labelset, shuffle, owner
+FROM tempquizzes;
+
+DROP TABLE tempquizzes;
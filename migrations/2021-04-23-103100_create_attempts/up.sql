@@ -0,0 +1,22 @@
+CREATE TABLE attempts
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    uuid TEXT UNIQUE NOT NULL,
+    quiz INTEGER NOT NULL,
+    userid INTEGER NOT NULL,
+    startedat BIGINT NOT NULL,
+    deadline BIGINT,
+    submittedat BIGINT,
+    score DOUBLE
+);
+
+CREATE TABLE answers
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    attempt INTEGER NOT NULL,
+    question INTEGER NOT NULL,
+    textanswer TEXT,
+    label INTEGER,
+    choices TEXT,
+    score DOUBLE NOT NULL
+);
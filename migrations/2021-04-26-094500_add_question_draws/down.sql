@@ -0,0 +1,42 @@
+ALTER TABLE quizzes RENAME TO tempquizzes;
+
+CREATE TABLE quizzes
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    uuid TEXT UNIQUE NOT NULL,
+    name TEXT NOT NULL,
+    labelset INTEGER NOT NULL,
+    shuffle SMALLINT NOT NULL,
+    owner INTEGER DEFAULT NULL,
+    opensat BIGINT DEFAULT NULL,
+    closesat BIGINT DEFAULT NULL,
+    timelimit INTEGER DEFAULT NULL
+);
+
+INSERT INTO quizzes
+    (id, uuid, name, labelset, shuffle, owner, opensat, closesat, timelimit)
+SELECT id, uuid, name, labelset, shuffle, owner, opensat, closesat, timelimit
+FROM tempquizzes;
+
+DROP TABLE tempquizzes;
+
+ALTER TABLE attempts RENAME TO tempattempts;
+
+CREATE TABLE attempts
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    uuid TEXT UNIQUE NOT NULL,
+    quiz INTEGER NOT NULL,
+    userid INTEGER NOT NULL,
+    startedat BIGINT NOT NULL,
+    deadline BIGINT,
+    submittedat BIGINT,
+    score DOUBLE
+);
+
+INSERT INTO attempts
+    (id, uuid, quiz, userid, startedat, deadline, submittedat, score)
+SELECT id, uuid, quiz, userid, startedat, deadline, submittedat, score
+FROM tempattempts;
+
+DROP TABLE tempattempts;
@@ -0,0 +1,12 @@
+-- Labels are recreated whenever their set is saved, so reviews refer to them by name.
+CREATE TABLE reviews
+(
+    userid INTEGER NOT NULL,
+    labelset INTEGER NOT NULL,
+    label TEXT NOT NULL,
+    easiness DOUBLE NOT NULL,
+    intervaldays INTEGER NOT NULL,
+    repetitions INTEGER NOT NULL,
+    due BIGINT NOT NULL,
+    PRIMARY KEY(userid, labelset, label)
+);
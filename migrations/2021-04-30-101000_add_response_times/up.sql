@@ -0,0 +1,20 @@
+ALTER TABLE answers RENAME TO tempanswers;
+
+CREATE TABLE answers
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    attempt INTEGER NOT NULL,
+    question INTEGER NOT NULL,
+    textanswer TEXT,
+    label INTEGER,
+    choices TEXT,
+    score DOUBLE NOT NULL,
+    responsetime DOUBLE
+);
+
+INSERT INTO answers
+    (id, attempt, question, textanswer, label, choices, score)
+SELECT id, attempt, question, textanswer, label, choices, score
+FROM tempanswers;
+
+DROP TABLE tempanswers;
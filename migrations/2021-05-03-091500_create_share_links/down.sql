@@ -0,0 +1,26 @@
+DROP TABLE sharelinks;
+
+DELETE FROM answers WHERE attempt IN (SELECT id FROM attempts WHERE userid IS NULL);
+
+ALTER TABLE attempts RENAME TO tempattempts;
+
+CREATE TABLE attempts
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    uuid TEXT UNIQUE NOT NULL,
+    quiz INTEGER NOT NULL,
+    userid INTEGER NOT NULL,
+    startedat BIGINT NOT NULL,
+    deadline BIGINT,
+    submittedat BIGINT,
+    score DOUBLE,
+    seed BIGINT NOT NULL DEFAULT 0
+);
+
+INSERT INTO attempts
+    (id, uuid, quiz, userid, startedat, deadline, submittedat, score, seed)
+SELECT id, uuid, quiz, userid, startedat, deadline, submittedat, score, seed
+FROM tempattempts
+WHERE userid IS NOT NULL;
+
+DROP TABLE tempattempts;
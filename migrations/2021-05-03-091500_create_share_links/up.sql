@@ -0,0 +1,36 @@
+CREATE TABLE sharelinks
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    token TEXT UNIQUE NOT NULL,
+    quiz INTEGER DEFAULT NULL,
+    labelset INTEGER DEFAULT NULL,
+    createdby INTEGER NOT NULL,
+    createdat BIGINT NOT NULL,
+    expiresat BIGINT DEFAULT NULL,
+    allowguests SMALLINT NOT NULL DEFAULT 0
+);
+
+-- Guests have no user, and are known by the nickname they give instead.
+ALTER TABLE attempts RENAME TO tempattempts;
+
+CREATE TABLE attempts
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    uuid TEXT UNIQUE NOT NULL,
+    quiz INTEGER NOT NULL,
+    userid INTEGER DEFAULT NULL,
+    startedat BIGINT NOT NULL,
+    deadline BIGINT,
+    submittedat BIGINT,
+    score DOUBLE,
+    seed BIGINT NOT NULL DEFAULT 0,
+    nickname TEXT DEFAULT NULL,
+    sharelink INTEGER DEFAULT NULL
+);
+
+INSERT INTO attempts
+    (id, uuid, quiz, userid, startedat, deadline, submittedat, score, seed)
+SELECT id, uuid, quiz, userid, startedat, deadline, submittedat, score, seed
+FROM tempattempts;
+
+DROP TABLE tempattempts;
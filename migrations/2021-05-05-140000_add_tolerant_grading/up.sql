@@ -0,0 +1,77 @@
+-- Lists of synonyms and accepted answers are stored one per line.
+ALTER TABLE labels RENAME TO templabels;
+
+CREATE TABLE labels
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    labelset INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    colour TEXT NOT NULL,
+    vertices BLOB NOT NULL,
+    vertexcount INTEGER DEFAULT NULL,
+    area DOUBLE DEFAULT NULL,
+    centroidx DOUBLE DEFAULT NULL,
+    centroidy DOUBLE DEFAULT NULL,
+    centroidz DOUBLE DEFAULT NULL,
+    minx DOUBLE DEFAULT NULL,
+    miny DOUBLE DEFAULT NULL,
+    minz DOUBLE DEFAULT NULL,
+    maxx DOUBLE DEFAULT NULL,
+    maxy DOUBLE DEFAULT NULL,
+    maxz DOUBLE DEFAULT NULL,
+    synonyms TEXT DEFAULT NULL
+);
+
+INSERT INTO labels
+    (id, labelset, name, colour, vertices, vertexcount, area, centroidx, centroidy, centroidz,
+    minx, miny, minz, maxx, maxy, maxz)
+SELECT id, labelset, name, colour, vertices, vertexcount, area, centroidx, centroidy, centroidz,
+       minx, miny, minz, maxx, maxy, maxz
+FROM templabels;
+
+DROP TABLE templabels;
+
+ALTER TABLE questions RENAME TO tempquestions;
+
+CREATE TABLE questions
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    quiz INTEGER NOT NULL,
+    questiontype SMALLINT NOT NULL,
+    textprompt TEXT NOT NULL,
+    textanswer TEXT,
+    label INTEGER,
+    showregions SMALLINT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    acceptedanswers TEXT DEFAULT NULL,
+    typotolerance INTEGER DEFAULT NULL
+);
+
+INSERT INTO questions
+    (id, quiz, questiontype, textprompt, textanswer, label, showregions, position)
+SELECT id, quiz, questiontype, textprompt, textanswer, label, showregions, position
+FROM tempquestions;
+
+DROP TABLE tempquestions;
+
+ALTER TABLE answers RENAME TO tempanswers;
+
+CREATE TABLE answers
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    attempt INTEGER NOT NULL,
+    question INTEGER NOT NULL,
+    textanswer TEXT,
+    label INTEGER,
+    choices TEXT,
+    score DOUBLE NOT NULL,
+    responsetime DOUBLE,
+    verdict SMALLINT DEFAULT NULL
+);
+
+INSERT INTO answers
+    (id, attempt, question, textanswer, label, choices, score, responsetime)
+SELECT id, attempt, question, textanswer, label, choices, score, responsetime
+FROM tempanswers;
+
+DROP TABLE tempanswers;
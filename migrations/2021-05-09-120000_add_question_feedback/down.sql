@@ -0,0 +1,53 @@
+DROP TABLE questionhints;
+
+DROP TABLE revealedhints;
+
+ALTER TABLE questions RENAME TO tempquestions;
+
+CREATE TABLE questions
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    quiz INTEGER NOT NULL,
+    questiontype SMALLINT NOT NULL,
+    textprompt TEXT NOT NULL,
+    textanswer TEXT,
+    label INTEGER,
+    showregions SMALLINT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    acceptedanswers TEXT DEFAULT NULL,
+    typotolerance INTEGER DEFAULT NULL,
+    overlapmeasure SMALLINT DEFAULT NULL,
+    passthreshold DOUBLE DEFAULT NULL
+);
+
+INSERT INTO questions
+    (id, quiz, questiontype, textprompt, textanswer, label, showregions, position,
+    acceptedanswers, typotolerance, overlapmeasure, passthreshold)
+SELECT id, quiz, questiontype, textprompt, textanswer, label, showregions, position,
+       acceptedanswers, typotolerance, overlapmeasure, passthreshold
+FROM tempquestions;
+
+DROP TABLE tempquestions;
+
+ALTER TABLE answers RENAME TO tempanswers;
+
+CREATE TABLE answers
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    attempt INTEGER NOT NULL,
+    question INTEGER NOT NULL,
+    textanswer TEXT,
+    label INTEGER,
+    choices TEXT,
+    score DOUBLE NOT NULL,
+    responsetime DOUBLE,
+    verdict SMALLINT DEFAULT NULL,
+    vertices TEXT DEFAULT NULL
+);
+
+INSERT INTO answers
+    (id, attempt, question, textanswer, label, choices, score, responsetime, verdict, vertices)
+SELECT id, attempt, question, textanswer, label, choices, score, responsetime, verdict, vertices
+FROM tempanswers;
+
+DROP TABLE tempanswers;
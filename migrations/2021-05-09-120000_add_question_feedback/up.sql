@@ -0,0 +1,68 @@
+CREATE TABLE questionhints
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    question INTEGER NOT NULL,
+    texthint TEXT NOT NULL,
+    penalty DOUBLE NOT NULL DEFAULT 0
+);
+
+CREATE TABLE revealedhints
+(
+    attempt INTEGER NOT NULL,
+    question INTEGER NOT NULL,
+    revealed INTEGER NOT NULL,
+    PRIMARY KEY (attempt, question)
+);
+
+ALTER TABLE questions RENAME TO tempquestions;
+
+CREATE TABLE questions
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    quiz INTEGER NOT NULL,
+    questiontype SMALLINT NOT NULL,
+    textprompt TEXT NOT NULL,
+    textanswer TEXT,
+    label INTEGER,
+    showregions SMALLINT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0,
+    acceptedanswers TEXT DEFAULT NULL,
+    typotolerance INTEGER DEFAULT NULL,
+    overlapmeasure SMALLINT DEFAULT NULL,
+    passthreshold DOUBLE DEFAULT NULL,
+    explanation TEXT DEFAULT NULL,
+    highlightlabels TEXT DEFAULT NULL
+);
+
+INSERT INTO questions
+    (id, quiz, questiontype, textprompt, textanswer, label, showregions, position,
+    acceptedanswers, typotolerance, overlapmeasure, passthreshold)
+SELECT id, quiz, questiontype, textprompt, textanswer, label, showregions, position,
+       acceptedanswers, typotolerance, overlapmeasure, passthreshold
+FROM tempquestions;
+
+DROP TABLE tempquestions;
+
+ALTER TABLE answers RENAME TO tempanswers;
+
+CREATE TABLE answers
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    attempt INTEGER NOT NULL,
+    question INTEGER NOT NULL,
+    textanswer TEXT,
+    label INTEGER,
+    choices TEXT,
+    score DOUBLE NOT NULL,
+    responsetime DOUBLE,
+    verdict SMALLINT DEFAULT NULL,
+    vertices TEXT DEFAULT NULL,
+    hintsused INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT INTO answers
+    (id, attempt, question, textanswer, label, choices, score, responsetime, verdict, vertices)
+SELECT id, attempt, question, textanswer, label, choices, score, responsetime, verdict, vertices
+FROM tempanswers;
+
+DROP TABLE tempanswers;
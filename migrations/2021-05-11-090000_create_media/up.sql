@@ -0,0 +1,20 @@
+CREATE TABLE media
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    uuid TEXT UNIQUE NOT NULL,
+    filename TEXT NOT NULL,
+    contenttype TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    owner INTEGER,
+    uploadedat BIGINT NOT NULL
+);
+
+-- Labels get new IDs every time their set is saved, so they are referred to by name.
+CREATE TABLE attachments
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    media INTEGER NOT NULL,
+    question INTEGER,
+    labelset INTEGER,
+    label TEXT
+);
@@ -0,0 +1,40 @@
+ALTER TABLE quizzes RENAME TO tempquizzes;
+
+CREATE TABLE quizzes
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    uuid TEXT UNIQUE NOT NULL,
+    name TEXT NOT NULL,
+    labelset INTEGER NOT NULL,
+    shuffle SMALLINT NOT NULL,
+    owner INTEGER DEFAULT NULL,
+    opensat BIGINT DEFAULT NULL,
+    closesat BIGINT DEFAULT NULL,
+    timelimit INTEGER DEFAULT NULL,
+    drawcount INTEGER DEFAULT NULL
+);
+
+INSERT INTO quizzes
+    (id, uuid, name, labelset, shuffle, owner, opensat, closesat, timelimit, drawcount)
+SELECT id, uuid, name, labelset, shuffle, owner, opensat, closesat, timelimit, drawcount
+FROM tempquizzes;
+
+DROP TABLE tempquizzes;
+
+ALTER TABLE labelsets RENAME TO templabelsets;
+
+CREATE TABLE labelsets
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    uuid TEXT UNIQUE NOT NULL,
+    name TEXT NOT NULL,
+    model INTEGER NOT NULL,
+    owner INTEGER DEFAULT NULL
+);
+
+INSERT INTO labelsets
+    (id, uuid, name, model, owner)
+SELECT id, uuid, name, model, owner
+FROM templabelsets;
+
+DROP TABLE templabelsets;
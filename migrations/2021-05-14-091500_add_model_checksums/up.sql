@@ -0,0 +1,22 @@
+-- `filename` is now the name the file is stored under, and `name` what it was uploaded as. Files
+-- uploaded before are kept under their own names, with their checksums unknown.
+ALTER TABLE models RENAME TO tempmodels;
+
+CREATE TABLE models
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    filename TEXT UNIQUE NOT NULL,
+    material TEXT DEFAULT NULL,
+    texture TEXT DEFAULT NULL,
+    category TEXT DEFAULT NULL,
+    name TEXT NOT NULL,
+    checksum TEXT DEFAULT NULL,
+    size BIGINT DEFAULT NULL
+);
+
+INSERT INTO models
+    (id, filename, material, texture, category, name)
+SELECT id, filename, material, texture, category, filename
+FROM tempmodels;
+
+DROP TABLE tempmodels;
@@ -0,0 +1,9 @@
+-- The questions drawn for each attempt, in the order they're asked. Attempts started before this
+-- have none, and are drawn again from their seed.
+CREATE TABLE attemptquestions
+(
+    attempt INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    question INTEGER NOT NULL,
+    PRIMARY KEY (attempt, position)
+);
@@ -0,0 +1,7 @@
+DELETE FROM searchindex WHERE kind = 'label';
+
+INSERT INTO searchindex
+    (kind, target, name, body)
+SELECT 'label', labelsets.uuid, labels.name, labelsets.name
+FROM labels
+JOIN labelsets ON labels.labelset = labelsets.id;
@@ -0,0 +1,9 @@
+-- Labels are found by their synonyms as well, which are kept along with the name of their set.
+DELETE FROM searchindex WHERE kind = 'label';
+
+INSERT INTO searchindex
+    (kind, target, name, body)
+SELECT 'label', labelsets.uuid, labels.name,
+    labelsets.name || COALESCE(' ' || REPLACE(labels.synonyms, char(10), ' '), '')
+FROM labels
+JOIN labelsets ON labels.labelset = labelsets.id;
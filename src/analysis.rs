@@ -0,0 +1,205 @@
+use serde::Serialize;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+};
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonOverlap {
+    pub first: String,
+    pub second: String,
+    pub vertices: Vec<usize>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonLabelSetAnalysis {
+    pub overlaps: Vec<JsonOverlap>,
+    pub empty_labels: Vec<String>,
+    pub labelled_vertices: usize,
+    /// Only known if the model could be read.
+    pub mesh_vertices: Option<usize>,
+    /// The share of the mesh's vertices not covered by any label, from 0 to 1.
+    pub unlabelled_share: Option<f64>,
+}
+
+/// Finds every pair of labels sharing vertices, given each label's name and vertices.
+pub fn overlaps(labels: &[(&str, Vec<usize>)]) -> Vec<JsonOverlap> {
+    let mut owners: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (label, (_, vertices)) in labels.iter().enumerate() {
+        let unique: HashSet<_> = vertices.iter().collect();
+        for &vertex in unique {
+            owners.entry(vertex).or_default().push(label);
+        }
+    }
+
+    // Ordered by label pair so that the report is stable between runs.
+    let mut pairs: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+    for (vertex, labels) in owners.into_iter().filter(|(_, l)| l.len() > 1) {
+        for (i, &first) in labels.iter().enumerate() {
+            for &second in &labels[i + 1..] {
+                pairs.entry((first, second)).or_default().push(vertex);
+            }
+        }
+    }
+
+    pairs
+        .into_iter()
+        .map(|((first, second), mut vertices)| {
+            vertices.sort_unstable();
+            JsonOverlap {
+                first: labels[first].0.to_owned(),
+                second: labels[second].0.to_owned(),
+                vertices,
+            }
+        })
+        .collect()
+}
+
+/// Reports overlapping and empty labels, and how much of the mesh is left unlabelled if the
+/// number of vertices in the mesh is known. Vertices outside the mesh are disregarded.
+pub fn analyse(
+    labels: &[(&str, Vec<usize>)],
+    mesh_vertices: Option<usize>,
+) -> JsonLabelSetAnalysis {
+    let labels: Vec<(&str, Vec<usize>)> = labels
+        .iter()
+        .map(|(name, vertices)| {
+            let vertices = vertices
+                .iter()
+                .copied()
+                .filter(|&v| mesh_vertices.map_or(true, |n| v < n))
+                .collect();
+            (*name, vertices)
+        })
+        .collect();
+
+    let empty_labels = labels
+        .iter()
+        .filter(|(_, vertices)| vertices.is_empty())
+        .map(|(name, _)| (*name).to_owned())
+        .collect();
+    let labelled_vertices = labels
+        .iter()
+        .flat_map(|(_, vertices)| vertices.iter())
+        .collect::<HashSet<_>>()
+        .len();
+    let unlabelled_share = mesh_vertices
+        .filter(|&n| n > 0)
+        .map(|n| (n - labelled_vertices) as f64 / n as f64);
+
+    JsonLabelSetAnalysis {
+        overlaps: overlaps(&labels),
+        empty_labels,
+        labelled_vertices,
+        mesh_vertices,
+        unlabelled_share,
+    }
+}
+
+/// A graded answer to a question, for item analysis.
+pub struct Response {
+    pub attempt: i32,
+    pub question: i32,
+    pub score: f64,
+    pub response_time: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonItemAnalysis {
+    pub attempts: usize,
+    pub questions: Vec<JsonItemStatistics>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonItemStatistics {
+    pub question: i32,
+    pub text_prompt: String,
+    pub responses: usize,
+    /// The mean score, from 0 for nobody answering correctly to 1 for everybody doing so.
+    pub difficulty: Option<f64>,
+    /// The difference in mean score between the best and worst 27% of attempts, from -1 to 1.
+    /// Questions that the best students get wrong more often than the worst are suspicious.
+    pub discrimination: Option<f64>,
+    /// In seconds, over the responses that were timed.
+    pub average_response_time: Option<f64>,
+}
+
+/// The share of attempts in each of the groups compared for the discrimination index.
+const DISCRIMINATION_GROUP_SHARE: f64 = 0.27;
+
+/// Computes the difficulty, discrimination and response time of each question, given as its ID
+/// and prompt. Attempts are ranked by the share of their questions answered correctly.
+pub fn item_analysis(questions: &[(i32, &str)], responses: &[Response]) -> JsonItemAnalysis {
+    fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+        let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+        if count == 0 {
+            None
+        } else {
+            Some(sum / f64::from(count))
+        }
+    }
+
+    let mut totals: HashMap<i32, (f64, usize)> = HashMap::new();
+    for r in responses {
+        let total = totals.entry(r.attempt).or_default();
+        total.0 += r.score;
+        total.1 += 1;
+    }
+    let mut ranking: Vec<(i32, f64)> = totals
+        .iter()
+        .map(|(&attempt, &(sum, count))| (attempt, sum / count as f64))
+        .collect();
+    ranking.sort_by(|a, b| {
+        a.1.partial_cmp(&b.1)
+            .unwrap_or(Ordering::Equal)
+            .then(a.0.cmp(&b.0))
+    });
+
+    // With a single attempt, it would be in both groups.
+    let group_size = if ranking.len() < 2 {
+        0
+    } else {
+        (ranking.len() as f64 * DISCRIMINATION_GROUP_SHARE).ceil() as usize
+    };
+    let lower: HashSet<i32> = ranking[..group_size].iter().map(|(a, _)| *a).collect();
+    let upper: HashSet<i32> = ranking[ranking.len() - group_size..]
+        .iter()
+        .map(|(a, _)| *a)
+        .collect();
+
+    let questions = questions
+        .iter()
+        .map(|&(question, prompt)| {
+            let answers: Vec<&Response> = responses
+                .iter()
+                .filter(|r| r.question == question)
+                .collect();
+            let group_mean = |group: &HashSet<i32>| {
+                mean(
+                    answers
+                        .iter()
+                        .filter(|r| group.contains(&r.attempt))
+                        .map(|r| r.score),
+                )
+            };
+            JsonItemStatistics {
+                question,
+                text_prompt: prompt.to_owned(),
+                responses: answers.len(),
+                difficulty: mean(answers.iter().map(|r| r.score)),
+                discrimination: group_mean(&upper)
+                    .and_then(|upper| group_mean(&lower).map(|lower| upper - lower)),
+                average_response_time: mean(answers.iter().filter_map(|r| r.response_time)),
+            }
+        })
+        .collect();
+
+    JsonItemAnalysis {
+        attempts: totals.len(),
+        questions,
+    }
+}
@@ -0,0 +1,812 @@
+use crate::{
+    authentication, geometry,
+    grading::{self, JsonRegionScore, Verdict},
+    models, publication,
+    quiz::{self, JsonDeliveredQuestion, JsonHint, JsonQuestion, JsonQuiz, QuestionKind},
+    schema::{
+        answers::dsl as answers_dsl, attemptquestions::dsl as attempt_questions_dsl,
+        attempts::dsl as attempts_dsl, quizzes::dsl as quizzes_dsl,
+        revealedhints::dsl as hints_dsl,
+    },
+    util, MainDbConn,
+};
+use diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl, SqliteConnection};
+use rocket::{get, http::Status, post, put, response::status};
+use rocket_contrib::{json::Json, uuid::Uuid};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+};
+
+/// Submissions arriving this many seconds after the deadline are still accepted, to make up for
+/// slow connections.
+const GRACE_SECONDS: i64 = 30;
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonAttempt {
+    pub uuid: String,
+    pub quiz: String,
+    /// The name a guest gave, for attempts made through a share link.
+    pub nickname: Option<String>,
+    pub started_at: i64,
+    pub deadline: Option<i64>,
+    pub submitted_at: Option<i64>,
+    pub remaining_seconds: Option<i64>,
+    /// The sum of the scores of the answers, once submitted.
+    pub score: Option<f64>,
+    /// The questions drawn for this attempt, in the order they're asked.
+    pub questions: Vec<JsonDeliveredQuestion>,
+    pub answers: Vec<JsonAnswer>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonAnswer {
+    pub question: i32,
+    pub text: Option<String>,
+    pub label_id: Option<i32>,
+    /// Indices of the chosen options of a multiple choice question.
+    #[serde(default)]
+    pub choices: Vec<i32>,
+    /// The vertices painted to locate a label, for questions scored by overlap.
+    #[serde(default)]
+    pub vertices: Option<Vec<usize>>,
+    /// Seconds spent on the question, as measured by the client.
+    #[serde(default)]
+    pub response_time: Option<f64>,
+    /// From 0 to 1, set when graded.
+    #[serde(default, skip_deserializing)]
+    pub score: Option<f64>,
+    /// How a written answer compared to the accepted answers, set when graded.
+    #[serde(default, skip_deserializing)]
+    pub verdict: Option<Verdict>,
+    /// How a painted region matched the label, set when graded.
+    #[serde(default, skip_deserializing)]
+    pub region: Option<JsonRegionScore>,
+    /// How many hints were revealed before answering, set when graded.
+    #[serde(default, skip_deserializing)]
+    pub hints_used: Option<i32>,
+}
+
+/// Either the attempt, or why it couldn't be started or submitted: `Forbidden` when it's not
+/// allowed, or `BadRequest` when the request was malformed.
+pub type AttemptResult = Result<Json<JsonAttempt>, status::Custom<Json<String>>>;
+
+/// Either the hint, or why it couldn't be revealed.
+pub type HintResult = Result<Json<JsonHint>, status::Forbidden<Json<String>>>;
+
+/// Who an attempt is made by.
+pub enum Participant<'a> {
+    User(i32),
+    /// A visitor without an account, let in through a share link.
+    Guest {
+        nickname: &'a str,
+        share_link: i32,
+    },
+}
+
+impl JsonAttempt {
+    fn from_db(
+        attempt: models::Attempt,
+        quiz: String,
+        questions: Vec<JsonDeliveredQuestion>,
+        answers: Vec<JsonAnswer>,
+    ) -> Self {
+        let remaining_seconds = match attempt.submittedat {
+            Some(_) => None,
+            None => remaining(attempt.deadline, util::now()),
+        };
+        Self {
+            uuid: attempt.uuid,
+            quiz,
+            nickname: attempt.nickname,
+            started_at: attempt.startedat,
+            deadline: attempt.deadline,
+            submitted_at: attempt.submittedat,
+            remaining_seconds,
+            score: attempt.score,
+            questions,
+            answers,
+        }
+    }
+}
+
+impl From<models::Answer> for JsonAnswer {
+    fn from(answer: models::Answer) -> Self {
+        Self {
+            question: answer.question,
+            text: answer.textanswer,
+            label_id: answer.label,
+            choices: answer
+                .choices
+                .map(|c| c.split(',').filter_map(|i| i.parse().ok()).collect())
+                .unwrap_or_default(),
+            vertices: answer.vertices.as_deref().map(geometry::parse_vertices),
+            response_time: answer.responsetime,
+            score: Some(answer.score),
+            verdict: answer.verdict.and_then(Verdict::from_db),
+            region: None,
+            hints_used: Some(answer.hintsused),
+        }
+    }
+}
+
+/// Draws the questions of an attempt from the quiz, using the attempt's seed. The pool is ordered
+/// by ID before drawing, so that reordering the quiz doesn't change which questions were drawn.
+/// The options of multiple choice questions are shuffled by the seed as well.
+pub fn draw_questions(quiz: JsonQuiz, seed: i64) -> Vec<JsonQuestion> {
+    let mut pool: Vec<(usize, JsonQuestion)> = quiz.questions.into_iter().enumerate().collect();
+    pool.sort_by_key(|(_, q)| q.id);
+    util::seeded_shuffle(&mut pool, seed);
+    if let Some(count) = quiz.draw_count {
+        pool.truncate(count.max(0) as usize);
+    }
+    if !quiz.shuffle {
+        pool.sort_by_key(|(position, _)| *position);
+    }
+    pool.into_iter()
+        .map(|(_, mut q)| {
+            q.shuffle_options(seed);
+            q
+        })
+        .collect()
+}
+
+/// The questions drawn for an attempt, in the order they're asked and with their options in the
+/// order they're shown. Questions removed from the quiz since are left out, and questions added
+/// since aren't asked.
+fn attempt_questions(
+    conn: &SqliteConnection,
+    attempt: &models::Attempt,
+    quiz: JsonQuiz,
+) -> QueryResult<Vec<JsonQuestion>> {
+    let drawn: Vec<i32> = attempt_questions_dsl::attemptquestions
+        .select(attempt_questions_dsl::question)
+        .filter(attempt_questions_dsl::attempt.eq(&attempt.id))
+        .order(attempt_questions_dsl::position)
+        .load(conn)?;
+
+    // Attempts started before the drawn questions were stored are drawn again from their seed.
+    if drawn.is_empty() {
+        return Ok(draw_questions(quiz, attempt.seed));
+    }
+    let mut questions: HashMap<i32, JsonQuestion> = quiz
+        .questions
+        .into_iter()
+        .filter_map(|q| Some((q.id?, q)))
+        .collect();
+    Ok(drawn
+        .iter()
+        .filter_map(|id| questions.remove(id))
+        .map(|mut q| {
+            q.shuffle_options(attempt.seed);
+            q
+        })
+        .collect())
+}
+
+/// Loads the drawn questions and any answers of an attempt. Painted regions are compared to
+/// their labels again, as only their score is stored. Until the attempt is submitted, the
+/// questions come without explanations, and with only the hints revealed so far. The answers
+/// to the questions are left out until the quiz closes.
+fn to_json(
+    conn: &SqliteConnection,
+    attempt: models::Attempt,
+    quiz: models::Quiz,
+) -> Result<JsonAttempt, Box<dyn Error>> {
+    let uuid = quiz.uuid.clone();
+    let reveal_answers = quiz::answers_revealed(&quiz);
+    let mut questions = attempt_questions(conn, &attempt, quiz::from_db(conn, quiz)?)?;
+    let mut answers: Vec<JsonAnswer> = answers_dsl::answers
+        .filter(answers_dsl::attempt.eq(&attempt.id))
+        .order(answers_dsl::id)
+        .load::<models::Answer>(conn)?
+        .into_iter()
+        .map(JsonAnswer::from)
+        .collect();
+
+    if attempt.submittedat.is_none() {
+        let revealed = revealed_hints(conn, attempt.id)?;
+        for question in questions.iter_mut() {
+            let id = question.id.unwrap_or_default();
+            question.withhold_feedback(revealed.get(&id).copied().unwrap_or(0) as usize);
+        }
+    }
+    let targets = region_targets(conn, &questions)?;
+    for answer in answers.iter_mut() {
+        if let Some(question) = questions.iter().find(|q| q.id == Some(answer.question)) {
+            answer.region = score_region(question, &targets, answer).map(|(_, region)| region);
+        }
+    }
+
+    let questions = questions
+        .into_iter()
+        .map(|q| q.deliver(reveal_answers))
+        .collect();
+    Ok(JsonAttempt::from_db(attempt, uuid, questions, answers))
+}
+
+/// The vertices of the labels that painted regions are scored against, by label ID.
+fn region_targets(
+    conn: &SqliteConnection,
+    questions: &[JsonQuestion],
+) -> QueryResult<HashMap<i32, Vec<usize>>> {
+    use crate::schema::labels::dsl as labels_dsl;
+
+    let label_ids: Vec<i32> = questions
+        .iter()
+        .filter_map(|q| match &q.kind {
+            QuestionKind::LocateRegion {
+                label_id,
+                overlap: Some(_),
+                ..
+            } => Some(*label_id),
+            _ => None,
+        })
+        .collect();
+    if label_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    Ok(labels_dsl::labels
+        .select((labels_dsl::id, labels_dsl::vertices))
+        .filter(labels_dsl::id.eq_any(&label_ids))
+        .load::<(i32, Vec<u8>)>(conn)?
+        .into_iter()
+        .map(|(id, vertices)| {
+            let vertices = geometry::parse_vertices(&String::from_utf8_lossy(&vertices));
+            (id, vertices)
+        })
+        .collect())
+}
+
+/// Scores the region painted in answer to a locate question that has an overlap measure.
+fn score_region(
+    question: &JsonQuestion,
+    targets: &HashMap<i32, Vec<usize>>,
+    answer: &JsonAnswer,
+) -> Option<(f64, JsonRegionScore)> {
+    match &question.kind {
+        QuestionKind::LocateRegion {
+            label_id,
+            overlap: Some(measure),
+            pass_threshold,
+        } => {
+            let painted = answer.vertices.as_ref()?;
+            let target = targets.get(label_id).map_or(&[][..], Vec::as_slice);
+            Some(grading::score_region(
+                painted,
+                target,
+                *measure,
+                pass_threshold.unwrap_or(grading::DEFAULT_PASS_THRESHOLD),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// How many hints have been revealed during an attempt, by question ID.
+fn revealed_hints(conn: &SqliteConnection, attempt: i32) -> QueryResult<HashMap<i32, i32>> {
+    Ok(hints_dsl::revealedhints
+        .select((hints_dsl::question, hints_dsl::revealed))
+        .filter(hints_dsl::attempt.eq(&attempt))
+        .load::<(i32, i32)>(conn)?
+        .into_iter()
+        .collect())
+}
+
+/// When an attempt must be submitted by, if ever. The quiz may have been closed early after the
+/// attempt was started.
+fn deadline(attempt: &models::Attempt, quiz: &models::Quiz) -> Option<i64> {
+    match (attempt.deadline, quiz.closesat) {
+        (Some(deadline), Some(closes)) => Some(deadline.min(closes)),
+        (deadline, closes) => deadline.or(closes),
+    }
+}
+
+/// Whether the user has submitted an attempt at the quiz.
+pub fn has_submitted(conn: &SqliteConnection, quiz: i32, user: i32) -> QueryResult<bool> {
+    let submitted = attempts_dsl::attempts
+        .select(attempts_dsl::id)
+        .filter(attempts_dsl::quiz.eq(&quiz))
+        .filter(attempts_dsl::userid.eq(&user))
+        .filter(attempts_dsl::submittedat.is_not_null())
+        .limit(1)
+        .load::<i32>(conn)?;
+    Ok(!submitted.is_empty())
+}
+
+/// Seconds left until `deadline`, if there is one.
+fn remaining(deadline: Option<i64>, now: i64) -> Option<i64> {
+    deadline.map(|deadline| (deadline - now).max(0))
+}
+
+/// Seconds the user has left to work on the quiz: what remains of their ongoing attempt, or the
+/// time until the quiz closes if they have none.
+pub fn remaining_seconds(
+    conn: &SqliteConnection,
+    quiz: &models::Quiz,
+    user: i32,
+) -> QueryResult<Option<i64>> {
+    let now = util::now();
+    let ongoing = attempts_dsl::attempts
+        .filter(attempts_dsl::quiz.eq(&quiz.id))
+        .filter(attempts_dsl::userid.eq(&user))
+        .filter(attempts_dsl::submittedat.is_null())
+        .filter(attempts_dsl::deadline.gt(now))
+        .order(attempts_dsl::deadline.desc())
+        .limit(1)
+        .load::<models::Attempt>(conn)?
+        .pop();
+
+    Ok(match ongoing {
+        Some(attempt) => remaining(attempt.deadline, now),
+        None => remaining(quiz.closesat, now),
+    })
+}
+
+/// Starts an attempt at a quiz, or resumes the user's ongoing attempt if they have one. The
+/// attempt must be submitted within the quiz's time limit, and before the quiz closes.
+#[post("/<uuid>")]
+pub fn start(
+    auth: &authentication::User,
+    conn: MainDbConn,
+    uuid: Uuid,
+) -> Result<Option<AttemptResult>, Box<dyn Error>> {
+    let quiz = quizzes_dsl::quizzes
+        .filter(quizzes_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<models::Quiz>(&*conn)?
+        .pop();
+    let quiz = match quiz {
+        Some(q) if publication::is_visible(&auth.0, q.status) => q,
+        _ => return Ok(None),
+    };
+
+    start_attempt(&*conn, quiz, Participant::User(auth.0.id)).map(Some)
+}
+
+/// Starts an attempt at a quiz. Users resume their ongoing attempt if they have one, while guests
+/// can't be told apart and always start over.
+pub fn start_attempt(
+    conn: &SqliteConnection,
+    quiz: models::Quiz,
+    participant: Participant,
+) -> Result<AttemptResult, Box<dyn Error>> {
+    let now = util::now();
+    if quiz.opensat.map_or(false, |opens| now < opens) {
+        return Ok(refused(Status::Forbidden, "The quiz hasn't opened yet."));
+    }
+    if quiz.closesat.map_or(false, |closes| now >= closes) {
+        return Ok(refused(Status::Forbidden, "The quiz has closed."));
+    }
+
+    // Starting over would otherwise reset the clock.
+    if let Participant::User(user) = participant {
+        let ongoing = attempts_dsl::attempts
+            .filter(attempts_dsl::quiz.eq(&quiz.id))
+            .filter(attempts_dsl::userid.eq(&user))
+            .filter(attempts_dsl::submittedat.is_null())
+            .load::<models::Attempt>(conn)?
+            .into_iter()
+            .find(|a| a.deadline.map_or(true, |deadline| now < deadline));
+        if let Some(attempt) = ongoing {
+            return Ok(Ok(Json(to_json(conn, attempt, quiz)?)));
+        }
+    }
+
+    let deadline = match (quiz.timelimit, quiz.closesat) {
+        (Some(limit), Some(closes)) => Some(closes.min(now + i64::from(limit))),
+        (Some(limit), None) => Some(now + i64::from(limit)),
+        (None, closes) => closes,
+    };
+    let (userid, nickname, sharelink) = match participant {
+        Participant::User(user) => (Some(user), None, None),
+        Participant::Guest {
+            nickname,
+            share_link,
+        } => (None, Some(nickname), Some(share_link)),
+    };
+    let attempt_uuid = util::create_uuid().to_string();
+    rocket_contrib::databases::diesel::insert_into(attempts_dsl::attempts)
+        .values(&models::NewAttempt {
+            uuid: &attempt_uuid,
+            quiz: quiz.id,
+            userid,
+            startedat: now,
+            deadline,
+            seed: util::random_seed(),
+            nickname,
+            sharelink,
+        })
+        .execute(conn)?;
+
+    let attempt = attempts_dsl::attempts
+        .filter(attempts_dsl::uuid.eq(&attempt_uuid))
+        .limit(1)
+        .load::<models::Attempt>(conn)?
+        .pop()
+        .ok_or("Can't find attempt that was just inserted.")?;
+
+    // The drawn questions are kept, so that the attempt can be reviewed as it was taken even after
+    // the quiz changes.
+    let drawn: Vec<models::AttemptQuestion> =
+        draw_questions(quiz::from_db(conn, quiz.clone())?, attempt.seed)
+            .iter()
+            .filter_map(|q| q.id)
+            .enumerate()
+            .map(|(position, question)| models::AttemptQuestion {
+                attempt: attempt.id,
+                position: position as i32,
+                question,
+            })
+            .collect();
+    rocket_contrib::databases::diesel::insert_into(attempt_questions_dsl::attemptquestions)
+        .values(&drawn)
+        .execute(conn)?;
+
+    Ok(Ok(Json(to_json(conn, attempt, quiz)?)))
+}
+
+#[get("/<uuid>")]
+pub fn load(
+    auth: &authentication::User,
+    conn: MainDbConn,
+    uuid: Uuid,
+) -> Result<Option<Json<JsonAttempt>>, Box<dyn Error>> {
+    match load_own(&*conn, &uuid, auth.0.id)? {
+        Some(attempt) => Ok(Some(Json(load_attempt(&*conn, attempt)?))),
+        None => Ok(None),
+    }
+}
+
+/// Loads an attempt along with its quiz.
+pub fn load_attempt(
+    conn: &SqliteConnection,
+    attempt: models::Attempt,
+) -> Result<JsonAttempt, Box<dyn Error>> {
+    let quiz = quizzes_dsl::quizzes
+        .find(&attempt.quiz)
+        .load::<models::Quiz>(conn)?
+        .pop()
+        .ok_or("The quiz of the attempt no longer exists.")?;
+
+    to_json(conn, attempt, quiz)
+}
+
+/// Grades and stores the answers to an attempt. An attempt can only be submitted once.
+#[put("/<uuid>", format = "json", data = "<answers>")]
+pub fn submit(
+    auth: &authentication::User,
+    conn: MainDbConn,
+    uuid: Uuid,
+    answers: Json<Vec<JsonAnswer>>,
+) -> Result<Option<AttemptResult>, Box<dyn Error>> {
+    match load_own(&*conn, &uuid, auth.0.id)? {
+        Some(attempt) => submit_attempt(&*conn, attempt, answers.into_inner()).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Grades and stores the answers to an attempt, unless it has been submitted already or its
+/// time has run out.
+pub fn submit_attempt(
+    conn: &SqliteConnection,
+    attempt: models::Attempt,
+    mut answers: Vec<JsonAnswer>,
+) -> Result<AttemptResult, Box<dyn Error>> {
+    if attempt.submittedat.is_some() {
+        return Ok(refused(
+            Status::Forbidden,
+            "The attempt has already been submitted.",
+        ));
+    }
+
+    let quiz = quizzes_dsl::quizzes
+        .find(&attempt.quiz)
+        .load::<models::Quiz>(conn)?
+        .pop()
+        .ok_or("The quiz of the attempt no longer exists.")?;
+
+    let now = util::now();
+    if deadline(&attempt, &quiz).map_or(false, |deadline| now > deadline + GRACE_SECONDS) {
+        return Ok(refused(
+            Status::Forbidden,
+            "The time for this attempt has run out.",
+        ));
+    }
+
+    let label_set = quiz.labelset;
+    let questions = attempt_questions(conn, &attempt, quiz::from_db(conn, quiz)?)?;
+    let grader = Grader::load(conn, label_set, &questions)?;
+    let revealed = revealed_hints(conn, attempt.id)?;
+
+    // Every drawn question gets an answer, left blank if it wasn't answered, so that skipped
+    // questions count against it in the statistics. Answers to other questions are ignored, as
+    // are repeated answers.
+    let graded: Vec<(JsonAnswer, f64)> = questions
+        .iter()
+        .filter_map(|q| q.id.map(|id| (q, id)))
+        .map(|(q, id)| {
+            let mut answer = answers
+                .iter()
+                .position(|a| a.question == id)
+                .map(|i| answers.swap_remove(i))
+                .unwrap_or(JsonAnswer {
+                    question: id,
+                    ..Default::default()
+                });
+            let (score, verdict) = grader.grade(q, &answer);
+            // Hints only cost what the answer earned.
+            let hints_used = revealed.get(&id).copied().unwrap_or(0);
+            let score = (score - q.hint_penalty(hints_used as usize)).max(0.0);
+            answer.verdict = verdict;
+            answer.hints_used = Some(hints_used);
+            (answer, score)
+        })
+        .collect();
+
+    let join = |items: Vec<String>| items.join(",");
+    let choices: Vec<String> = graded
+        .iter()
+        .map(|(a, _)| join(a.choices.iter().map(|c| c.to_string()).collect()))
+        .collect();
+    let vertices: Vec<Option<String>> = graded
+        .iter()
+        .map(|(a, _)| {
+            a.vertices
+                .as_ref()
+                .map(|v| join(v.iter().map(|v| v.to_string()).collect()))
+        })
+        .collect();
+    let new_answers: Vec<_> = graded
+        .iter()
+        .zip(choices.iter().zip(vertices.iter()))
+        .map(|((answer, score), (choices, vertices))| models::NewAnswer {
+            attempt: attempt.id,
+            question: answer.question,
+            textanswer: answer.text.as_deref(),
+            label: answer.label_id,
+            choices: Some(choices.as_ref()).filter(|c: &&str| !c.is_empty()),
+            score: *score,
+            verdict: answer.verdict.map(Verdict::to_db),
+            vertices: vertices.as_deref(),
+            hintsused: answer.hints_used.unwrap_or(0),
+            responsetime: answer.response_time.filter(|t| t.is_finite() && *t >= 0.0),
+        })
+        .collect();
+    rocket_contrib::databases::diesel::insert_into(answers_dsl::answers)
+        .values(&new_answers)
+        .execute(conn)?;
+
+    let score: f64 = graded.iter().map(|(_, score)| score).sum();
+    rocket_contrib::databases::diesel::update(attempts_dsl::attempts.find(&attempt.id))
+        .set((
+            attempts_dsl::submittedat.eq(now),
+            attempts_dsl::score.eq(score),
+        ))
+        .execute(conn)?;
+
+    let attempt = attempts_dsl::attempts
+        .find(&attempt.id)
+        .load::<models::Attempt>(conn)?
+        .pop()
+        .ok_or("Can't find attempt that was just submitted.")?;
+    Ok(Ok(Json(load_attempt(conn, attempt)?)))
+}
+
+/// Reveals the next hint to a question of the user's attempt.
+#[post("/<uuid>/hints/<question>")]
+pub fn hint(
+    auth: &authentication::User,
+    conn: MainDbConn,
+    uuid: Uuid,
+    question: i32,
+) -> Result<Option<HintResult>, Box<dyn Error>> {
+    match load_own(&*conn, &uuid, auth.0.id)? {
+        Some(attempt) => reveal_hint(&*conn, attempt, question),
+        None => Ok(None),
+    }
+}
+
+/// Reveals the next hint to a question drawn for an attempt, while the attempt can still be
+/// submitted. Revealed hints take their penalty off the score of the answer.
+pub fn reveal_hint(
+    conn: &SqliteConnection,
+    attempt: models::Attempt,
+    question: i32,
+) -> Result<Option<HintResult>, Box<dyn Error>> {
+    if attempt.submittedat.is_some() {
+        return Ok(Some(forbidden("The attempt has already been submitted.")));
+    }
+
+    let quiz = quizzes_dsl::quizzes
+        .find(&attempt.quiz)
+        .load::<models::Quiz>(conn)?
+        .pop()
+        .ok_or("The quiz of the attempt no longer exists.")?;
+    if deadline(&attempt, &quiz).map_or(false, |deadline| util::now() > deadline) {
+        return Ok(Some(forbidden("The time for this attempt has run out.")));
+    }
+
+    let questions = attempt_questions(conn, &attempt, quiz::from_db(conn, quiz)?)?;
+    let question = match questions.into_iter().find(|q| q.id == Some(question)) {
+        Some(q) => q,
+        None => return Ok(None),
+    };
+    let revealed = revealed_hints(conn, attempt.id)?
+        .get(&question.id.unwrap_or_default())
+        .copied()
+        .unwrap_or(0);
+    let hint = match question.hints.into_iter().nth(revealed as usize) {
+        Some(h) => h,
+        None => return Ok(Some(forbidden("There are no more hints to this question."))),
+    };
+
+    rocket_contrib::databases::diesel::replace_into(hints_dsl::revealedhints)
+        .values(&models::RevealedHint {
+            attempt: attempt.id,
+            question: question.id.unwrap_or_default(),
+            revealed: revealed + 1,
+        })
+        .execute(conn)?;
+    Ok(Some(Ok(Json(hint))))
+}
+
+/// What answers to the questions of a quiz are graded against.
+pub struct Grader {
+    /// The names of the labels in the set, each followed by its synonyms.
+    label_names: HashMap<i32, Vec<String>>,
+    /// The vertices of the labels that painted regions are scored against.
+    region_targets: HashMap<i32, Vec<usize>>,
+}
+
+impl Grader {
+    /// Loads the labels of the set that the questions need to be graded.
+    pub fn load(
+        conn: &SqliteConnection,
+        label_set: i32,
+        questions: &[JsonQuestion],
+    ) -> QueryResult<Self> {
+        use crate::schema::labels::dsl as labels_dsl;
+
+        let label_names = labels_dsl::labels
+            .select((labels_dsl::id, labels_dsl::name, labels_dsl::synonyms))
+            .filter(labels_dsl::labelset.eq(&label_set))
+            .load::<(i32, String, Option<String>)>(conn)?
+            .into_iter()
+            .map(|(id, name, synonyms)| {
+                let mut names = vec![name];
+                names.extend(util::split_lines(synonyms.as_deref()));
+                (id, names)
+            })
+            .collect();
+
+        Ok(Self {
+            label_names,
+            region_targets: region_targets(conn, questions)?,
+        })
+    }
+
+    /// The name of a label in the set.
+    pub fn label_name(&self, label_id: i32) -> Option<&str> {
+        self.label_names
+            .get(&label_id)
+            .map(|names| names[0].as_str())
+    }
+
+    /// Scores an answer from 0 to 1. Written answers are graded by `grading::grade_text`, accepting
+    /// the answer of the question and its other accepted answers, along with the synonyms of the
+    /// label it names. The names of the other labels in the set are never taken for typos. Painted
+    /// regions get partial credit by `grading::score_region`.
+    pub fn grade(&self, question: &JsonQuestion, answer: &JsonAnswer) -> (f64, Option<Verdict>) {
+        let label_names = &self.label_names;
+        let written = |expected: &str, label: Option<i32>| {
+            let mut accepted = vec![expected];
+            accepted.extend(question.accepted_answers.iter().map(String::as_str));
+            // The synonyms of a label only apply when the label's name is what's asked for.
+            if let Some(names) = label.and_then(|l| label_names.get(&l)) {
+                if grading::normalise(&names[0]) == grading::normalise(expected) {
+                    accepted.extend(names.iter().map(String::as_str));
+                }
+            }
+            let rejected: Vec<&str> = label_names
+                .values()
+                .flatten()
+                .map(String::as_str)
+                .filter(|name| !accepted.contains(name))
+                .collect();
+            let tolerance = question.typo_tolerance.map(|t| t.max(0) as usize);
+            let verdict = grading::grade_text(
+                answer.text.as_deref().unwrap_or_default(),
+                &accepted,
+                &rejected,
+                tolerance,
+            );
+            (verdict.is_correct(), Some(verdict))
+        };
+        let (correct, verdict) = match &question.kind {
+            QuestionKind::NameRegion {
+                label_id,
+                text_answer,
+            } => {
+                let name = label_names.get(label_id).map(|names| names[0].as_str());
+                match text_answer.as_deref().or(name) {
+                    Some(expected) => written(expected, Some(*label_id)),
+                    None => (false, Some(Verdict::Wrong)),
+                }
+            }
+            QuestionKind::LocateRegion { label_id, .. } => {
+                // Answers picking a label are still accepted when the region could be painted.
+                match score_region(question, &self.region_targets, answer) {
+                    Some((score, _)) => return (score, None),
+                    None => (answer.label_id == Some(*label_id), None),
+                }
+            }
+            QuestionKind::FreeText {
+                text_answer,
+                label_id,
+            } => written(text_answer, *label_id),
+            QuestionKind::MultipleChoice { options, .. } => {
+                let correct: BTreeSet<i32> = (0..)
+                    .zip(options.iter())
+                    .filter(|(_, o)| o.correct)
+                    .map(|(i, _)| i)
+                    .collect();
+                (correct == answer.choices.iter().copied().collect(), None)
+            }
+        };
+        if correct {
+            (1.0, verdict)
+        } else {
+            (0.0, verdict)
+        }
+    }
+}
+
+/// Deletes the attempts at a quiz along with their answers and revealed hints.
+pub fn delete_for_quiz(conn: &SqliteConnection, quiz_id: i32) -> QueryResult<()> {
+    let attempt_ids: Vec<i32> = attempts_dsl::attempts
+        .select(attempts_dsl::id)
+        .filter(attempts_dsl::quiz.eq(&quiz_id))
+        .load(conn)?;
+
+    rocket_contrib::databases::diesel::delete(answers_dsl::answers)
+        .filter(answers_dsl::attempt.eq_any(&attempt_ids))
+        .execute(conn)?;
+    rocket_contrib::databases::diesel::delete(hints_dsl::revealedhints)
+        .filter(hints_dsl::attempt.eq_any(&attempt_ids))
+        .execute(conn)?;
+    rocket_contrib::databases::diesel::delete(attempt_questions_dsl::attemptquestions)
+        .filter(attempt_questions_dsl::attempt.eq_any(&attempt_ids))
+        .execute(conn)?;
+    rocket_contrib::databases::diesel::delete(attempts_dsl::attempts)
+        .filter(attempts_dsl::quiz.eq(&quiz_id))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+fn load_own(
+    conn: &SqliteConnection,
+    uuid: &Uuid,
+    user: i32,
+) -> QueryResult<Option<models::Attempt>> {
+    Ok(attempts_dsl::attempts
+        .filter(attempts_dsl::uuid.eq(&uuid.to_string()))
+        .filter(attempts_dsl::userid.eq(&user))
+        .limit(1)
+        .load::<models::Attempt>(conn)?
+        .pop())
+}
+
+fn forbidden<T>(reason: &str) -> Result<T, status::Forbidden<Json<String>>> {
+    Err(status::Forbidden(Some(Json(reason.to_owned()))))
+}
+
+/// Refuses a request with the given status and reason.
+pub fn refused<T>(status: Status, reason: &str) -> Result<T, status::Custom<Json<String>>> {
+    Err(status::Custom(status, Json(reason.to_owned())))
+}
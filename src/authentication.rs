@@ -56,6 +56,13 @@ impl<'a, 'r> FromRequest<'a, 'r> for Moderator {
 
 pub struct Admin(pub models::User);
 
+/// Whether the user may change or share what belongs to the given owner. Things without an owner
+/// can be changed by any moderator, and administrators can change anything.
+pub fn may_edit(user: &models::User, owner: Option<i32>) -> bool {
+    let is_admin = user.privilege == models::Privilege::Administrator as i32;
+    is_admin || owner.map_or(true, |owner| owner == user.id)
+}
+
 impl<'a, 'r> FromRequest<'a, 'r> for Admin {
     type Error = !;
 
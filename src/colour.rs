@@ -0,0 +1,260 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+/// An opaque sRGB colour, always written out as a lowercase `#rrggbb` string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Colour {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// Neutral grey, used where a stored colour can't be understood.
+impl Default for Colour {
+    fn default() -> Self {
+        Self::new(0x80, 0x80, 0x80)
+    }
+}
+
+/// The CSS level 2 named colours.
+const NAMED_COLOURS: &[(&str, Colour)] = &[
+    ("black", Colour::new(0x00, 0x00, 0x00)),
+    ("silver", Colour::new(0xc0, 0xc0, 0xc0)),
+    ("gray", Colour::new(0x80, 0x80, 0x80)),
+    ("grey", Colour::new(0x80, 0x80, 0x80)),
+    ("white", Colour::new(0xff, 0xff, 0xff)),
+    ("maroon", Colour::new(0x80, 0x00, 0x00)),
+    ("red", Colour::new(0xff, 0x00, 0x00)),
+    ("purple", Colour::new(0x80, 0x00, 0x80)),
+    ("fuchsia", Colour::new(0xff, 0x00, 0xff)),
+    ("green", Colour::new(0x00, 0x80, 0x00)),
+    ("lime", Colour::new(0x00, 0xff, 0x00)),
+    ("olive", Colour::new(0x80, 0x80, 0x00)),
+    ("yellow", Colour::new(0xff, 0xff, 0x00)),
+    ("navy", Colour::new(0x00, 0x00, 0x80)),
+    ("blue", Colour::new(0x00, 0x00, 0xff)),
+    ("teal", Colour::new(0x00, 0x80, 0x80)),
+    ("aqua", Colour::new(0x00, 0xff, 0xff)),
+    ("orange", Colour::new(0xff, 0xa5, 0x00)),
+];
+
+/// The Okabe-Ito palette without black, which is hard to see on a shaded model.
+const OKABE_ITO: &[Colour] = &[
+    Colour::new(0xe6, 0x9f, 0x00),
+    Colour::new(0x56, 0xb4, 0xe9),
+    Colour::new(0x00, 0x9e, 0x73),
+    Colour::new(0xf0, 0xe4, 0x42),
+    Colour::new(0x00, 0x72, 0xb2),
+    Colour::new(0xd5, 0x5e, 0x00),
+    Colour::new(0xcc, 0x79, 0xa7),
+];
+
+/// Colour vision deficiency simulation matrices in linear RGB (Machado et al., 2009), for
+/// protanopia, deuteranopia and tritanopia respectively.
+const CVD_MATRICES: [[[f64; 3]; 3]; 3] = [
+    [
+        [0.152286, 1.052583, -0.204868],
+        [0.114503, 0.786281, 0.099216],
+        [-0.003882, -0.048116, 1.051998],
+    ],
+    [
+        [0.367322, 0.860646, -0.227968],
+        [0.280085, 0.672501, 0.047413],
+        [-0.011820, 0.042940, 0.968881],
+    ],
+    [
+        [1.255528, -0.076749, -0.178779],
+        [-0.078411, 0.930809, 0.147602],
+        [0.004733, 0.691367, 0.303900],
+    ],
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseColourError(String);
+
+impl fmt::Display for ParseColourError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid colour '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseColourError {}
+
+impl Colour {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Converts to CIELAB (D65) for perceptual distance calculations.
+    fn to_lab(self) -> [f64; 3] {
+        linear_to_lab(self.to_linear())
+    }
+
+    fn to_linear(self) -> [f64; 3] {
+        let channel = |c: u8| {
+            let c = f64::from(c) / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        [channel(self.r), channel(self.g), channel(self.b)]
+    }
+
+    /// CIE76 distance between two colours as seen with normal vision and with each of the three
+    /// simulated colour vision deficiencies, whichever is smallest.
+    fn min_distance(self, other: Colour) -> f64 {
+        let (a, b) = (self.to_linear(), other.to_linear());
+        let mut distance = delta_e(linear_to_lab(a), linear_to_lab(b));
+        for matrix in CVD_MATRICES.iter() {
+            let d = delta_e(
+                linear_to_lab(apply_matrix(matrix, a)),
+                linear_to_lab(apply_matrix(matrix, b)),
+            );
+            distance = distance.min(d);
+        }
+        distance
+    }
+}
+
+impl fmt::Display for Colour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl FromStr for Colour {
+    type Err = ParseColourError;
+
+    /// Accepts `#rgb`, `#rrggbb` (with or without the `#`), `rgb(r, g, b)` and CSS colour names.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseColourError(s.to_owned());
+        let trimmed = s.trim().to_ascii_lowercase();
+
+        if let Some((_, colour)) = NAMED_COLOURS.iter().find(|(name, _)| *name == trimmed) {
+            return Ok(*colour);
+        }
+
+        if let Some(args) = trimmed
+            .strip_prefix("rgb(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let channels = args
+                .split(',')
+                .map(|c| c.trim().parse::<u8>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| error())?;
+            return match channels.as_slice() {
+                [r, g, b] => Ok(Self::new(*r, *g, *b)),
+                _ => Err(error()),
+            };
+        }
+
+        let hex = trimmed.strip_prefix('#').unwrap_or(&trimmed);
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(error());
+        }
+        let channel = |s: &str| u8::from_str_radix(s, 16).map_err(|_| error());
+        match hex.len() {
+            3 => {
+                let short = |i: usize| channel(&hex[i..=i]).map(|c| c * 0x11);
+                Ok(Self::new(short(0)?, short(1)?, short(2)?))
+            }
+            6 => Ok(Self::new(
+                channel(&hex[0..2])?,
+                channel(&hex[2..4])?,
+                channel(&hex[4..6])?,
+            )),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl Serialize for Colour {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Colour {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Suggests `n` colours that are easy to tell apart, also for colour blind users.
+///
+/// The first colours come from the Okabe-Ito palette. Beyond that, colours are picked greedily
+/// from a grid of candidates, each time choosing the one furthest away from all colours picked so
+/// far under normal vision and simulated protanopia, deuteranopia and tritanopia. Very dark and
+/// very light candidates are skipped as they are hard to see on a shaded model.
+pub fn palette(n: usize) -> Vec<Colour> {
+    let mut chosen: Vec<Colour> = OKABE_ITO.iter().copied().take(n).collect();
+    if chosen.len() == n {
+        return chosen;
+    }
+
+    const STEPS: [u8; 6] = [0x00, 0x33, 0x66, 0x99, 0xcc, 0xff];
+    let mut candidates: Vec<Colour> = STEPS
+        .iter()
+        .flat_map(|&r| {
+            STEPS
+                .iter()
+                .flat_map(move |&g| STEPS.iter().map(move |&b| Colour::new(r, g, b)))
+        })
+        .filter(|c| {
+            let lightness = c.to_lab()[0];
+            (25.0..=90.0).contains(&lightness)
+        })
+        .collect();
+
+    while chosen.len() < n {
+        let best = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let distance = chosen
+                    .iter()
+                    .map(|other| c.min_distance(*other))
+                    .fold(f64::INFINITY, f64::min);
+                (i, distance)
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        match best {
+            Some((i, _)) => chosen.push(candidates.swap_remove(i)),
+            // Out of candidates, so repeat the palette from the start.
+            None => chosen.push(chosen[chosen.len() % OKABE_ITO.len()]),
+        }
+    }
+
+    chosen
+}
+
+fn apply_matrix(m: &[[f64; 3]; 3], c: [f64; 3]) -> [f64; 3] {
+    let row = |r: &[f64; 3]| (r[0] * c[0] + r[1] * c[1] + r[2] * c[2]).clamp(0.0, 1.0);
+    [row(&m[0]), row(&m[1]), row(&m[2])]
+}
+
+fn linear_to_lab([r, g, b]: [f64; 3]) -> [f64; 3] {
+    // Linear sRGB to XYZ, normalised by the D65 white point.
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f64| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn delta_e(a: [f64; 3], b: [f64; 3]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
@@ -0,0 +1,449 @@
+use crate::{
+    authentication,
+    quiz::{self, JsonOption, JsonQuestion, JsonQuiz, QuestionKind},
+    schema::{labels::dsl as labels_dsl, labelsets::dsl as labelsets_dsl},
+    util, MainDbConn,
+};
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use rocket::{
+    get,
+    http::ContentType,
+    post,
+    response::{status, Content},
+    Data,
+};
+use rocket_contrib::{json::Json, uuid::Uuid};
+use serde::Serialize;
+use std::{collections::HashMap, error::Error, io::Read};
+
+pub mod moodle;
+pub mod qti;
+
+const MIB: u64 = 1024u64.pow(2);
+const IMPORT_SIZE_LIMIT: u64 = 20 * MIB;
+
+/// The namespace of the elements referring back to label sets in exported region questions.
+pub const NAMESPACE: &str = "https://github.com/luringens/Open-Anatomy-Explorer";
+
+/// What could be read from an exported quiz.
+pub struct Imported {
+    pub name: Option<String>,
+    pub questions: Vec<JsonQuestion>,
+    /// Why each of the questions that couldn't be imported was skipped.
+    pub skipped: Vec<String>,
+}
+
+/// The label set of a quiz being exported, needed to name the labels that questions refer to.
+pub struct LabelSetContext<'a> {
+    pub uuid: &'a str,
+    pub labels: &'a HashMap<i32, String>,
+}
+
+impl LabelSetContext<'_> {
+    fn label_name(&self, label_id: i32) -> &str {
+        self.labels.get(&label_id).map_or("", String::as_str)
+    }
+
+    /// The text of an option, which is the name of its label unless it has a text of its own.
+    fn option_text(&self, option: &JsonOption) -> String {
+        match (&option.text, option.label_id) {
+            (Some(text), _) => text.clone(),
+            (None, Some(label)) => self.label_name(label).to_owned(),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+/// The label a region question is about, and its kind by the name used in `questionType`.
+fn region(kind: &QuestionKind) -> Option<(&'static str, i32)> {
+    match kind {
+        QuestionKind::NameRegion { label_id, .. } => Some(("nameRegion", *label_id)),
+        QuestionKind::LocateRegion { label_id, .. } => Some(("locateRegion", *label_id)),
+        _ => None,
+    }
+}
+
+/// A free text question accepting any of `answers`, the first being its answer. There must be
+/// at least one.
+fn free_text(prompt: String, mut answers: Vec<String>) -> JsonQuestion {
+    let answer = answers.remove(0);
+    JsonQuestion {
+        id: None,
+        kind: QuestionKind::FreeText {
+            text_answer: answer,
+            label_id: None,
+        },
+        text_prompt: prompt,
+        show_regions: None,
+        accepted_answers: answers,
+        typo_tolerance: None,
+        explanation: None,
+        hints: Vec::new(),
+        highlight_labels: Vec::new(),
+        attachments: Vec::new(),
+    }
+}
+
+fn multiple_choice(prompt: String, options: Vec<(String, bool)>) -> JsonQuestion {
+    JsonQuestion {
+        id: None,
+        kind: QuestionKind::MultipleChoice {
+            options: options
+                .into_iter()
+                .map(|(text, correct)| JsonOption {
+                    text: Some(text),
+                    label_id: None,
+                    correct,
+                })
+                .collect(),
+            distractors: None,
+        },
+        text_prompt: prompt,
+        show_regions: None,
+        accepted_answers: Vec::new(),
+        typo_tolerance: None,
+        explanation: None,
+        hints: Vec::new(),
+        highlight_labels: Vec::new(),
+        attachments: Vec::new(),
+    }
+}
+
+/// Reduces HTML to its text, collapsing whitespace.
+pub fn plain_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    collapse_whitespace(&text)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonImport {
+    pub uuid: String,
+    pub imported: usize,
+    pub skipped: Vec<String>,
+}
+
+/// Either the imported quiz, or what stopped it from being imported.
+pub type ImportResult = Result<Json<JsonImport>, status::BadRequest<Json<Vec<String>>>>;
+
+/// Exports a quiz as a QTI 2.1 package (`qti`) or as Moodle XML (`moodle`). Region questions
+/// can't be answered outside of the viewer, and are exported with a reference to their label
+/// set instead.
+#[get("/<uuid>/export/<format>")]
+pub fn export(
+    _auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+    format: String,
+) -> Result<Option<Content<Vec<u8>>>, Box<dyn Error>> {
+    use crate::schema::quizzes::dsl as quizzes_dsl;
+
+    let quiz = quizzes_dsl::quizzes
+        .filter(quizzes_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<crate::models::Quiz>(&*conn)?
+        .pop();
+    let quiz: JsonQuiz = match quiz {
+        Some(q) => quiz::from_db(&*conn, q)?,
+        None => return Ok(None),
+    };
+
+    let label_set_uuid = labelsets_dsl::labelsets
+        .find(&quiz.label_set)
+        .select(labelsets_dsl::uuid)
+        .load::<String>(&*conn)?
+        .pop()
+        .unwrap_or_default();
+    let labels: HashMap<i32, String> = labels_dsl::labels
+        .select((labels_dsl::id, labels_dsl::name))
+        .filter(labels_dsl::labelset.eq(&quiz.label_set))
+        .load(&*conn)?
+        .into_iter()
+        .collect();
+    let context = LabelSetContext {
+        uuid: &label_set_uuid,
+        labels: &labels,
+    };
+
+    Ok(match format.as_ref() {
+        "qti" => Some(Content(ContentType::ZIP, qti::export(&quiz, &context)?)),
+        "moodle" => Some(Content(
+            ContentType::XML,
+            moodle::export(&quiz, &context).into_bytes(),
+        )),
+        _ => None,
+    })
+}
+
+/// Imports the text and multiple choice questions of a QTI 2.1 package or item (`qti`) or of
+/// Moodle XML (`moodle`) into a new quiz on the given label set.
+#[post("/import/<format>?<labelset>&<name>", data = "<data>")]
+pub fn import(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    format: String,
+    labelset: Uuid,
+    name: Option<String>,
+    data: Data,
+) -> Result<Option<ImportResult>, Box<dyn Error>> {
+    let label_set = labelsets_dsl::labelsets
+        .filter(labelsets_dsl::uuid.eq(&labelset.to_string()))
+        .limit(1)
+        .load::<crate::models::LabelSet>(&*conn)?
+        .pop();
+    let label_set = match label_set {
+        Some(set) => set,
+        None => return Ok(None),
+    };
+
+    let mut bytes = Vec::new();
+    data.open()
+        .take(IMPORT_SIZE_LIMIT)
+        .read_to_end(&mut bytes)?;
+    let imported = match format.as_ref() {
+        "qti" => qti::import(&bytes),
+        "moodle" => moodle::import(&bytes),
+        _ => return Ok(None),
+    };
+    let imported = match imported {
+        Ok(i) if !i.questions.is_empty() => i,
+        Ok(i) => {
+            let mut errors = vec!["Found no questions that could be imported.".to_owned()];
+            errors.extend(i.skipped);
+            return Ok(Some(Err(status::BadRequest(Some(Json(errors))))));
+        }
+        Err(e) => return Ok(Some(Err(status::BadRequest(Some(Json(vec![e])))))),
+    };
+
+    let Imported {
+        name: imported_name,
+        questions,
+        skipped,
+    } = imported;
+    let quiz = JsonQuiz {
+        id: None,
+        name: name
+            .or(imported_name)
+            .unwrap_or_else(|| "Imported quiz".to_owned()),
+        label_set: label_set.id,
+        shuffle: false,
+        draw_count: None,
+        opens_at: None,
+        closes_at: None,
+        time_limit: None,
+        status: None,
+        remaining_seconds: None,
+        questions,
+        skipped: Vec::new(),
+    };
+    let count = quiz.questions.len();
+
+    Ok(
+        quiz::add(auth, conn, util::create_uuid(), quiz)?.map(|result| {
+            result.map(|Json(uuid)| {
+                Json(JsonImport {
+                    uuid,
+                    imported: count,
+                    skipped,
+                })
+            })
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question(kind: QuestionKind, prompt: &str, accepted: &[&str]) -> JsonQuestion {
+        JsonQuestion {
+            id: None,
+            kind,
+            text_prompt: prompt.to_owned(),
+            show_regions: None,
+            accepted_answers: accepted.iter().map(|a| (*a).to_owned()).collect(),
+            typo_tolerance: None,
+            explanation: None,
+            hints: Vec::new(),
+            highlight_labels: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    fn quiz() -> JsonQuiz {
+        let free_text = question(
+            QuestionKind::FreeText {
+                text_answer: "Radial nerve".to_owned(),
+                label_id: None,
+            },
+            "Which nerve runs in the <spiral> groove & wraps the humerus?",
+            &["N. radialis"],
+        );
+        let multiple_choice = question(
+            QuestionKind::MultipleChoice {
+                options: vec![
+                    JsonOption {
+                        text: Some("Ulnar \"funny bone\" nerve".to_owned()),
+                        label_id: None,
+                        correct: false,
+                    },
+                    JsonOption {
+                        text: None,
+                        label_id: Some(1),
+                        correct: true,
+                    },
+                ],
+                distractors: None,
+            },
+            "Which nerve supplies the triceps?",
+            &[],
+        );
+        let region = question(
+            QuestionKind::NameRegion {
+                label_id: 1,
+                text_answer: None,
+            },
+            "Name the highlighted nerve.",
+            &[],
+        );
+        JsonQuiz {
+            id: None,
+            name: "Nerves of the arm".to_owned(),
+            label_set: 1,
+            shuffle: false,
+            draw_count: None,
+            opens_at: None,
+            closes_at: None,
+            time_limit: None,
+            status: None,
+            remaining_seconds: None,
+            questions: vec![free_text, multiple_choice, region],
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Checks that the text and multiple choice questions came back as they were exported, with
+    /// options named by their label, and that the region question was skipped.
+    fn assert_round_trip(imported: Imported) {
+        assert_eq!(imported.name.as_deref(), Some("Nerves of the arm"));
+        assert_eq!(imported.skipped.len(), 1, "{:?}", imported.skipped);
+        assert_eq!(imported.questions.len(), 2);
+
+        let free_text = &imported.questions[0];
+        assert_eq!(
+            free_text.text_prompt,
+            "Which nerve runs in the <spiral> groove & wraps the humerus?"
+        );
+        assert_eq!(
+            free_text.kind,
+            QuestionKind::FreeText {
+                text_answer: "Radial nerve".to_owned(),
+                label_id: None,
+            }
+        );
+        assert_eq!(free_text.accepted_answers, vec!["N. radialis".to_owned()]);
+
+        let multiple_choice = &imported.questions[1];
+        assert_eq!(
+            multiple_choice.text_prompt,
+            "Which nerve supplies the triceps?"
+        );
+        assert_eq!(
+            multiple_choice.kind,
+            QuestionKind::MultipleChoice {
+                options: vec![
+                    JsonOption {
+                        text: Some("Ulnar \"funny bone\" nerve".to_owned()),
+                        label_id: None,
+                        correct: false,
+                    },
+                    JsonOption {
+                        text: Some("Radial nerve".to_owned()),
+                        label_id: None,
+                        correct: true,
+                    },
+                ],
+                distractors: None,
+            }
+        );
+    }
+
+    fn labels() -> HashMap<i32, String> {
+        vec![(1, "Radial nerve".to_owned())].into_iter().collect()
+    }
+
+    #[test]
+    fn moodle_round_trip() {
+        let labels = labels();
+        let context = LabelSetContext {
+            uuid: "aaaa",
+            labels: &labels,
+        };
+        let exported = moodle::export(&quiz(), &context);
+        assert_round_trip(moodle::import(exported.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn qti_round_trip() {
+        let labels = labels();
+        let context = LabelSetContext {
+            uuid: "aaaa",
+            labels: &labels,
+        };
+        let exported = qti::export(&quiz(), &context).unwrap();
+        assert_round_trip(qti::import(&exported).unwrap());
+    }
+
+    #[test]
+    fn imports_reject_other_files() {
+        assert!(moodle::import(b"<notaquiz/>").is_err());
+        assert!(moodle::import(b"Question 1: radial nerve").is_err());
+        assert!(qti::import(b"PK not really a zip").is_err());
+        assert!(qti::import(&[0xff, 0xfe]).is_err());
+    }
+
+    #[test]
+    fn plain_text_strips_tags() {
+        assert_eq!(
+            plain_text("<p>Name the <b>radial</b> nerve.</p>"),
+            "Name the radial nerve."
+        );
+        assert_eq!(plain_text("Line<br/>break"), "Line break");
+        assert_eq!(plain_text("<p>\n  Spread\t out \n</p>"), "Spread out");
+    }
+
+    #[test]
+    fn plain_text_decodes_entities() {
+        assert_eq!(plain_text("a&nbsp;&amp;&nbsp;b"), "a & b");
+        assert_eq!(plain_text("&lt;p&gt; isn't a tag"), "<p> isn't a tag");
+        assert_eq!(
+            plain_text("&quot;quoted&quot; &#39;too&#39;"),
+            "\"quoted\" 'too'"
+        );
+        // Decoded once, so escaped entities stay escaped.
+        assert_eq!(plain_text("&amp;lt;"), "&lt;");
+    }
+}
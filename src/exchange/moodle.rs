@@ -0,0 +1,222 @@
+//! Moodle XML, as described at https://docs.moodle.org/en/Moodle_XML_format.
+
+use super::{
+    collapse_whitespace, free_text, multiple_choice, plain_text, region, Imported, LabelSetContext,
+};
+use crate::{
+    quiz::{JsonHint, JsonQuestion, JsonQuiz, QuestionKind},
+    util::escape_xml,
+};
+use roxmltree::{Document, Node};
+
+/// Prefixes of the tags referring region questions back to their label set.
+const TAG_KIND: &str = "oae-kind:";
+const TAG_LABEL_SET: &str = "oae-labelset:";
+const TAG_LABEL: &str = "oae-label:";
+
+/// Writes free text questions as short answer questions and multiple choice questions as such.
+/// Region questions become descriptions, tagged with their kind, label set and label.
+/// Explanations become general feedback.
+pub fn export(quiz: &JsonQuiz, context: &LabelSetContext) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<quiz>\n");
+    xml.push_str(&format!(
+        "  <question type=\"category\">\n    <category><text>$course$/{}</text></category>\n  </question>\n",
+        escape_xml(&quiz.name)
+    ));
+
+    for (i, question) in quiz.questions.iter().enumerate() {
+        let kind = match &question.kind {
+            QuestionKind::FreeText { .. } => "shortanswer",
+            QuestionKind::MultipleChoice { .. } => "multichoice",
+            QuestionKind::NameRegion { .. } | QuestionKind::LocateRegion { .. } => "description",
+        };
+        xml.push_str(&format!("  <question type=\"{}\">\n", kind));
+        xml.push_str(&format!(
+            "    <name><text>Question {}</text></name>\n",
+            i + 1
+        ));
+        xml.push_str(&format!(
+            "    <questiontext format=\"plain_text\"><text>{}</text></questiontext>\n",
+            escape_xml(&question.text_prompt)
+        ));
+        if let Some(explanation) = &question.explanation {
+            xml.push_str(&format!(
+                "    <generalfeedback format=\"plain_text\"><text>{}</text></generalfeedback>\n",
+                escape_xml(explanation)
+            ));
+        }
+        // Moodle has no penalties for hints, only for each wrong try.
+        for hint in &question.hints {
+            xml.push_str(&format!(
+                "    <hint format=\"plain_text\"><text>{}</text></hint>\n",
+                escape_xml(&hint.text)
+            ));
+        }
+
+        match &question.kind {
+            QuestionKind::FreeText { text_answer, .. } => {
+                xml.push_str("    <usecase>0</usecase>\n");
+                xml.push_str(&answer(100.0, text_answer));
+                for accepted in &question.accepted_answers {
+                    xml.push_str(&answer(100.0, accepted));
+                }
+            }
+            QuestionKind::MultipleChoice { options, .. } => {
+                let correct = options.iter().filter(|o| o.correct).count();
+                xml.push_str(&format!(
+                    "    <single>{}</single>\n    <shuffleanswers>true</shuffleanswers>\n",
+                    correct == 1
+                ));
+                for option in options {
+                    // The fractions of the correct options have to add up to 100%.
+                    let fraction = if option.correct {
+                        100.0 / correct as f64
+                    } else {
+                        0.0
+                    };
+                    xml.push_str(&answer(fraction, &context.option_text(option)));
+                }
+            }
+            kind => {
+                if let Some((kind, label)) = region(kind) {
+                    xml.push_str(&format!(
+                        "    <tags>\n      <tag><text>{}{}</text></tag>\n      <tag><text>{}{}</text></tag>\n      <tag><text>{}{}</text></tag>\n    </tags>\n",
+                        TAG_KIND,
+                        kind,
+                        TAG_LABEL_SET,
+                        escape_xml(context.uuid),
+                        TAG_LABEL,
+                        escape_xml(context.label_name(label))
+                    ));
+                }
+            }
+        }
+        xml.push_str("  </question>\n");
+    }
+
+    xml.push_str("</quiz>\n");
+    xml
+}
+
+fn answer(fraction: f64, text: &str) -> String {
+    format!(
+        "    <answer fraction=\"{}\" format=\"plain_text\"><text>{}</text></answer>\n",
+        (fraction * 100_000.0).round() / 100_000.0,
+        escape_xml(text)
+    )
+}
+
+/// Reads short answer, multiple choice and true/false questions, with their general feedback and
+/// hints. The name of the quiz is taken from the last category.
+pub fn import(data: &[u8]) -> Result<Imported, String> {
+    let text = std::str::from_utf8(data).map_err(|_| "The file isn't valid UTF-8.")?;
+    let document = Document::parse(text).map_err(|e| format!("The file isn't valid XML: {}", e))?;
+    let root = document.root_element();
+    if !root.has_tag_name("quiz") {
+        return Err("The file isn't Moodle XML.".to_owned());
+    }
+
+    let mut imported = Imported {
+        name: None,
+        questions: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let (categories, questions): (Vec<_>, Vec<_>) = root
+        .children()
+        .filter(|n| n.has_tag_name("question"))
+        .partition(|n| n.attribute("type") == Some("category"));
+    imported.name = categories.last().and_then(|category| {
+        child_text(*category, "category")
+            .rsplit('/')
+            .next()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_owned)
+    });
+
+    for (i, question) in questions.into_iter().enumerate() {
+        let kind = question.attribute("type").unwrap_or_default();
+        let prompt = child_text(question, "questiontext");
+        let explanation = Some(child_text(question, "generalfeedback")).filter(|e| !e.is_empty());
+        let hints: Vec<JsonHint> = question
+            .children()
+            .filter(|n| n.has_tag_name("hint"))
+            .map(|n| JsonHint {
+                text: child_text(n, "hint"),
+                penalty: 0.0,
+            })
+            .filter(|h| !h.text.is_empty())
+            .collect();
+        let answers: Vec<(String, f64)> = question
+            .children()
+            .filter(|n| n.has_tag_name("answer"))
+            .map(|n| {
+                let fraction = n
+                    .attribute("fraction")
+                    .and_then(|f| f.parse().ok())
+                    .unwrap_or(0.0);
+                (child_text(n, "answer"), fraction)
+            })
+            .collect();
+
+        let imported_question = match kind {
+            "shortanswer" => {
+                let correct: Vec<String> = answers
+                    .into_iter()
+                    .filter(|(_, f)| *f >= 100.0)
+                    .map(|(answer, _)| answer)
+                    .collect();
+                if correct.is_empty() {
+                    imported.skipped.push(format!(
+                        "Question {}: there is no fully correct answer.",
+                        i + 1
+                    ));
+                    continue;
+                }
+                free_text(prompt, correct)
+            }
+            "multichoice" | "truefalse" => {
+                let options = answers.into_iter().map(|(t, f)| (t, f > 0.0)).collect();
+                multiple_choice(prompt, options)
+            }
+            kind => {
+                imported.skipped.push(format!(
+                    "Question {}: {} questions can't be imported.",
+                    i + 1,
+                    kind
+                ));
+                continue;
+            }
+        };
+        imported.questions.push(JsonQuestion {
+            explanation,
+            hints,
+            ..imported_question
+        });
+    }
+
+    Ok(imported)
+}
+
+/// The text of the `text` element within the first child named `name`, or within `node` itself
+/// if that's what is named `name`. HTML, which is the default format, is reduced to plain text.
+fn child_text(node: Node, name: &str) -> String {
+    let container = if node.has_tag_name(name) {
+        Some(node)
+    } else {
+        node.children().find(|n| n.has_tag_name(name))
+    };
+    let container = match container {
+        Some(c) => c,
+        None => return String::new(),
+    };
+    let text = container
+        .children()
+        .find(|n| n.has_tag_name("text"))
+        .and_then(|t| t.text())
+        .unwrap_or_default();
+    match container.attribute("format").unwrap_or("html") {
+        "html" => plain_text(text),
+        _ => collapse_whitespace(text),
+    }
+}
@@ -0,0 +1,380 @@
+//! IMS QTI 2.1 content packages, as described at https://www.imsglobal.org/question/.
+
+use super::{
+    collapse_whitespace, free_text, multiple_choice, region, Imported, LabelSetContext,
+    IMPORT_SIZE_LIMIT, NAMESPACE,
+};
+use crate::{
+    quiz::{JsonQuestion, JsonQuiz, QuestionKind},
+    util::escape_xml,
+};
+use roxmltree::{Document, Node};
+use std::{
+    error::Error,
+    io::{Cursor, Read, Write},
+};
+
+const QTI_NAMESPACE: &str = "http://www.imsglobal.org/xsd/imsqti_v2p1";
+const CP_NAMESPACE: &str = "http://www.imsglobal.org/xsd/imscp_v1p1";
+const MATCH_CORRECT: &str = "http://www.imsglobal.org/question/qti_v2p1/rptemplates/match_correct";
+const MAP_RESPONSE: &str = "http://www.imsglobal.org/question/qti_v2p1/rptemplates/map_response";
+
+/// Writes a content package with one item per question, a test listing them in order and a
+/// manifest. Region questions become custom interactions referring to their label set.
+pub fn export(quiz: &JsonQuiz, context: &LabelSetContext) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+
+    let mut resources = String::new();
+    let mut item_refs = String::new();
+    for (i, question) in quiz.questions.iter().enumerate() {
+        let identifier = format!("item{}", i + 1);
+        let file = format!("{}.xml", identifier);
+        zip.start_file(file.as_str(), options)?;
+        zip.write_all(item(&identifier, i, question, context).as_bytes())?;
+
+        resources.push_str(&format!(
+            "    <resource identifier=\"{0}\" type=\"imsqti_item_xmlv2p1\" href=\"{1}\">\n      <file href=\"{1}\"/>\n    </resource>\n",
+            identifier, file
+        ));
+        item_refs.push_str(&format!(
+            "      <assessmentItemRef identifier=\"{0}\" href=\"{0}.xml\"/>\n",
+            identifier
+        ));
+    }
+
+    zip.start_file("test.xml", options)?;
+    zip.write_all(
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<assessmentTest xmlns=\"{}\" identifier=\"test\" title=\"{}\">\n  <testPart identifier=\"part\" navigationMode=\"nonlinear\" submissionMode=\"simultaneous\">\n    <assessmentSection identifier=\"section\" title=\"{1}\" visible=\"true\">\n{}    </assessmentSection>\n  </testPart>\n</assessmentTest>\n",
+            QTI_NAMESPACE,
+            escape_xml(&quiz.name),
+            item_refs
+        )
+        .as_bytes(),
+    )?;
+
+    zip.start_file("imsmanifest.xml", options)?;
+    zip.write_all(
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<manifest xmlns=\"{}\" identifier=\"manifest\">\n  <organizations/>\n  <resources>\n    <resource identifier=\"test\" type=\"imsqti_test_xmlv2p1\" href=\"test.xml\">\n      <file href=\"test.xml\"/>\n    </resource>\n{}  </resources>\n</manifest>\n",
+            CP_NAMESPACE, resources
+        )
+        .as_bytes(),
+    )?;
+
+    Ok(zip.finish()?.into_inner())
+}
+
+fn item(
+    identifier: &str,
+    index: usize,
+    question: &JsonQuestion,
+    context: &LabelSetContext,
+) -> String {
+    let prompt = escape_xml(&question.text_prompt);
+    let (declaration, body) = match &question.kind {
+        QuestionKind::FreeText { text_answer, .. } => (
+            format!(
+                "  <responseDeclaration identifier=\"RESPONSE\" cardinality=\"single\" baseType=\"string\">\n    <correctResponse><value>{}</value></correctResponse>\n{}  </responseDeclaration>\n",
+                escape_xml(text_answer),
+                mapping(text_answer, &question.accepted_answers)
+            ),
+            format!(
+                "    <p>{}</p>\n    <textEntryInteraction responseIdentifier=\"RESPONSE\"/>\n",
+                prompt
+            ),
+        ),
+        QuestionKind::MultipleChoice { options, .. } => {
+            let single = options.iter().filter(|o| o.correct).count() == 1;
+            let correct: String = options
+                .iter()
+                .enumerate()
+                .filter(|(_, o)| o.correct)
+                .map(|(i, _)| format!("<value>choice{}</value>", i))
+                .collect();
+            let choices: String = options
+                .iter()
+                .enumerate()
+                .map(|(i, o)| {
+                    format!(
+                        "      <simpleChoice identifier=\"choice{}\">{}</simpleChoice>\n",
+                        i,
+                        escape_xml(&context.option_text(o))
+                    )
+                })
+                .collect();
+            (
+                format!(
+                    "  <responseDeclaration identifier=\"RESPONSE\" cardinality=\"{}\" baseType=\"identifier\">\n    <correctResponse>{}</correctResponse>\n  </responseDeclaration>\n",
+                    if single { "single" } else { "multiple" },
+                    correct
+                ),
+                format!(
+                    "    <choiceInteraction responseIdentifier=\"RESPONSE\" shuffle=\"true\" maxChoices=\"{}\">\n      <prompt>{}</prompt>\n{}    </choiceInteraction>\n",
+                    if single { 1 } else { 0 },
+                    prompt,
+                    choices
+                ),
+            )
+        }
+        kind => {
+            let (kind, label) = region(kind).unwrap_or_default();
+            (
+                "  <responseDeclaration identifier=\"RESPONSE\" cardinality=\"single\" baseType=\"string\"/>\n".to_owned(),
+                format!(
+                    "    <p>{}</p>\n    <customInteraction responseIdentifier=\"RESPONSE\">\n      <oae:region xmlns:oae=\"{}\" kind=\"{}\" labelSet=\"{}\" label=\"{}\"/>\n    </customInteraction>\n",
+                    prompt,
+                    NAMESPACE,
+                    kind,
+                    escape_xml(context.uuid),
+                    escape_xml(context.label_name(label))
+                ),
+            )
+        }
+    };
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<assessmentItem xmlns=\"{}\" identifier=\"{}\" title=\"Question {}\" adaptive=\"false\" timeDependent=\"false\">\n{}  <outcomeDeclaration identifier=\"SCORE\" cardinality=\"single\" baseType=\"float\"/>\n  <itemBody>\n{}  </itemBody>\n  <responseProcessing template=\"{}\"/>\n</assessmentItem>\n",
+        QTI_NAMESPACE,
+        identifier,
+        index + 1,
+        declaration,
+        body,
+        if question.accepted_answers.is_empty() {
+            MATCH_CORRECT
+        } else {
+            MAP_RESPONSE
+        }
+    )
+}
+
+/// Scores every accepted answer as correct, for questions accepting more than one answer.
+fn mapping(answer: &str, accepted: &[String]) -> String {
+    if accepted.is_empty() {
+        return String::new();
+    }
+    let entries: String = std::iter::once(answer)
+        .chain(accepted.iter().map(String::as_str))
+        .map(|a| {
+            format!(
+                "      <mapEntry mapKey=\"{}\" mappedValue=\"1\" caseSensitive=\"false\"/>\n",
+                escape_xml(a)
+            )
+        })
+        .collect();
+    format!(
+        "    <mapping defaultValue=\"0\" upperBound=\"1\">\n{}    </mapping>\n",
+        entries
+    )
+}
+
+/// Reads the choice and text entry items of a content package, in the order of its manifest,
+/// or a single item. The name of the quiz is taken from the title of the test, if any.
+pub fn import(data: &[u8]) -> Result<Imported, String> {
+    let mut imported = Imported {
+        name: None,
+        questions: Vec::new(),
+        skipped: Vec::new(),
+    };
+
+    if !data.starts_with(b"PK") {
+        let text = std::str::from_utf8(data).map_err(|_| "The file isn't valid UTF-8.")?;
+        let document = parse(text, "The file")?;
+        import_item(&document, "The item", &mut imported);
+        return Ok(imported);
+    }
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))
+        .map_err(|e| format!("The file isn't a valid package: {}", e))?;
+    let mut files = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive
+            .by_index(i)
+            .map_err(|e| format!("The package couldn't be read: {}", e))?;
+        if file.is_dir() || !file.name().ends_with(".xml") {
+            continue;
+        }
+        let name = file.name().to_owned();
+        let mut text = String::new();
+        (&mut file)
+            .take(IMPORT_SIZE_LIMIT)
+            .read_to_string(&mut text)
+            .map_err(|_| format!("{} isn't valid UTF-8.", name))?;
+        files.push((name, text));
+    }
+
+    let file = |href: &str| files.iter().find(|(name, _)| name == href);
+    let (mut items, mut tests) = (Vec::new(), Vec::new());
+    match file("imsmanifest.xml") {
+        Some((_, manifest)) => {
+            let manifest = parse(manifest, "The manifest")?;
+            for resource in manifest
+                .descendants()
+                .filter(|n| n.has_tag_name("resource"))
+            {
+                let href = resource.attribute("href").unwrap_or_default();
+                match resource.attribute("type").unwrap_or_default() {
+                    t if t.starts_with("imsqti_item") => items.push(href.to_owned()),
+                    t if t.starts_with("imsqti_test") => tests.push(href.to_owned()),
+                    _ => {}
+                }
+            }
+        }
+        None => {
+            items = files.iter().map(|(name, _)| name.clone()).collect();
+            items.sort();
+        }
+    }
+
+    imported.name = tests
+        .iter()
+        .filter_map(|href| file(href))
+        .filter_map(|(_, text)| Document::parse(text).ok())
+        .filter_map(|test| {
+            test.root_element()
+                .attribute("title")
+                .map(collapse_whitespace)
+        })
+        .find(|title| !title.is_empty());
+
+    for href in items {
+        match file(&href) {
+            Some((name, text)) => {
+                let document = parse(text, name)?;
+                if document.root_element().has_tag_name("assessmentItem") {
+                    import_item(&document, name, &mut imported);
+                }
+            }
+            None => imported
+                .skipped
+                .push(format!("{} is missing from the package.", href)),
+        }
+    }
+
+    Ok(imported)
+}
+
+fn parse<'a>(text: &'a str, name: &str) -> Result<Document<'a>, String> {
+    Document::parse(text).map_err(|e| format!("{} isn't valid XML: {}", name, e))
+}
+
+fn import_item(document: &Document, name: &str, imported: &mut Imported) {
+    let root = document.root_element();
+    if !root.has_tag_name("assessmentItem") {
+        imported
+            .skipped
+            .push(format!("{} isn't an assessment item.", name));
+        return;
+    }
+    let body = match root.children().find(|n| n.has_tag_name("itemBody")) {
+        Some(body) => body,
+        None => {
+            imported.skipped.push(format!("{} has no item body.", name));
+            return;
+        }
+    };
+
+    let interactions: Vec<Node> = body
+        .descendants()
+        .filter(|n| n.is_element() && n.tag_name().name().ends_with("Interaction"))
+        .collect();
+    let interaction = match interactions.as_slice() {
+        [interaction] => *interaction,
+        [] => {
+            imported
+                .skipped
+                .push(format!("{} has no interaction.", name));
+            return;
+        }
+        _ => {
+            imported
+                .skipped
+                .push(format!("{} has more than one interaction.", name));
+            return;
+        }
+    };
+
+    let response = interaction
+        .attribute("responseIdentifier")
+        .unwrap_or_default();
+    let declaration = root.children().find(|n| {
+        n.has_tag_name("responseDeclaration") && n.attribute("identifier") == Some(response)
+    });
+    let correct: Vec<String> = declaration
+        .and_then(|d| d.children().find(|n| n.has_tag_name("correctResponse")))
+        .map(|c| {
+            c.children()
+                .filter(|n| n.has_tag_name("value"))
+                .map(|v| v.text().unwrap_or_default().trim().to_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let prompt = interaction
+        .children()
+        .find(|n| n.has_tag_name("prompt"))
+        .map(text)
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| text(body));
+
+    match interaction.tag_name().name() {
+        "choiceInteraction" => {
+            let options = interaction
+                .descendants()
+                .filter(|n| n.has_tag_name("simpleChoice"))
+                .map(|choice| {
+                    let identifier = choice.attribute("identifier").unwrap_or_default();
+                    (text(choice), correct.iter().any(|c| c == identifier))
+                })
+                .collect();
+            imported.questions.push(multiple_choice(prompt, options));
+        }
+        "textEntryInteraction" | "extendedTextInteraction" => {
+            // Answers mapped to a positive score are accepted too, and can stand in for a
+            // missing correct response.
+            let mut answers: Vec<String> = correct.into_iter().filter(|c| !c.is_empty()).collect();
+            let mapped = declaration
+                .into_iter()
+                .flat_map(|d| d.descendants())
+                .filter(|n| n.has_tag_name("mapEntry"))
+                .filter(|n| {
+                    n.attribute("mappedValue")
+                        .and_then(|v| v.parse::<f64>().ok())
+                        .map_or(false, |v| v > 0.0)
+                })
+                .filter_map(|n| n.attribute("mapKey"));
+            for key in mapped {
+                if !answers.iter().any(|a| a == key) {
+                    answers.push(key.to_owned());
+                }
+            }
+            if answers.is_empty() {
+                imported
+                    .skipped
+                    .push(format!("{} has no correct response.", name));
+            } else {
+                imported.questions.push(free_text(prompt, answers));
+            }
+        }
+        kind => imported
+            .skipped
+            .push(format!("{}: {} items can't be imported.", name, kind)),
+    }
+}
+
+/// The text of a node, leaving out any interactions within it.
+fn text(node: Node) -> String {
+    let mut text = String::new();
+    for n in node.descendants() {
+        let in_interaction = n
+            .ancestors()
+            .take_while(|a| *a != node)
+            .any(|a| a.is_element() && a.tag_name().name().ends_with("Interaction"));
+        if n.is_text() && !in_interaction {
+            text.push_str(n.text().unwrap_or_default());
+            text.push(' ');
+        }
+    }
+    collapse_whitespace(&text)
+}
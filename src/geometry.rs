@@ -0,0 +1,153 @@
+use serde::Serialize;
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+};
+
+/// The vertex positions and triangulated faces of an OBJ model. Label vertices are indices into
+/// `positions`, in the order the `v` lines appear in the file.
+pub struct Mesh {
+    pub positions: Vec<[f64; 3]>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelGeometry {
+    pub vertex_count: i32,
+    pub area: f64,
+    pub centroid: [f64; 3],
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+impl Mesh {
+    /// Loads an OBJ file from `MODELS_DIR`.
+    pub fn load(filename: &str) -> Result<Mesh, Box<dyn Error>> {
+        let path = PathBuf::from(std::env::var("MODELS_DIR")?).join(filename);
+        Self::parse_obj(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads vertex positions and faces, ignoring everything else. Polygons are split into
+    /// triangle fans.
+    pub fn parse_obj<R: BufRead>(reader: R) -> Result<Mesh, Box<dyn Error>> {
+        let mut positions = Vec::new();
+        let mut triangles = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            let mut parts = line.split_whitespace();
+            match parts.next() {
+                Some("v") => {
+                    let mut position = [0.0; 3];
+                    for p in position.iter_mut() {
+                        *p = parts
+                            .next()
+                            .ok_or("Vertex with too few coordinates")?
+                            .parse()?;
+                    }
+                    positions.push(position);
+                }
+                Some("f") => {
+                    let face = parts
+                        .map(|p| {
+                            // Faces are `v`, `v/vt`, `v//vn` or `v/vt/vn`, and may count from
+                            // the end with negative indices.
+                            let index: i64 = p.split('/').next().unwrap_or_default().parse()?;
+                            let index = if index < 0 {
+                                positions.len() as i64 + index
+                            } else {
+                                index - 1
+                            };
+                            if index < 0 {
+                                return Err("Face refers to a vertex before the first".into());
+                            }
+                            Ok(index as usize)
+                        })
+                        .collect::<Result<Vec<usize>, Box<dyn Error>>>()?;
+                    for i in 1..face.len().saturating_sub(1) {
+                        triangles.push([face[0], face[i], face[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Mesh {
+            positions,
+            triangles,
+        })
+    }
+
+    /// Summarises the part of the mesh covered by a label. Indices outside the mesh are ignored,
+    /// and `None` is returned if no vertices remain. The area counts every triangle that has all
+    /// three corners within the label.
+    pub fn summarise(&self, vertices: &[usize]) -> Option<LabelGeometry> {
+        let mut included = vec![false; self.positions.len()];
+        for &v in vertices.iter().filter(|&&v| v < self.positions.len()) {
+            included[v] = true;
+        }
+
+        let mut count = 0;
+        let mut sum = [0.0; 3];
+        let mut min = [f64::INFINITY; 3];
+        let mut max = [f64::NEG_INFINITY; 3];
+        for (position, _) in self
+            .positions
+            .iter()
+            .zip(included.iter())
+            .filter(|(_, &included)| included)
+        {
+            count += 1;
+            for axis in 0..3 {
+                sum[axis] += position[axis];
+                min[axis] = min[axis].min(position[axis]);
+                max[axis] = max[axis].max(position[axis]);
+            }
+        }
+        if count == 0 {
+            return None;
+        }
+
+        let area = self
+            .triangles
+            .iter()
+            .filter(|t| t.iter().all(|&v| included.get(v).copied().unwrap_or(false)))
+            .map(|&[a, b, c]| {
+                let (a, b, c) = (self.positions[a], self.positions[b], self.positions[c]);
+                triangle_area(a, b, c)
+            })
+            .sum();
+
+        let n = f64::from(count);
+        Some(LabelGeometry {
+            vertex_count: count,
+            area,
+            centroid: [sum[0] / n, sum[1] / n, sum[2] / n],
+            min,
+            max,
+        })
+    }
+}
+
+/// Parses the vertex list of a label, which is a comma separated list of indices that may be
+/// wrapped in brackets. Anything that isn't an index is skipped.
+pub fn parse_vertices(vertices: &str) -> Vec<usize> {
+    vertices
+        .split(|c: char| c == ',' || c == '[' || c == ']' || c.is_whitespace())
+        .filter_map(|v| v.parse().ok())
+        .collect()
+}
+
+fn triangle_area(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> f64 {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let cross = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    0.5 * (cross[0].powi(2) + cross[1].powi(2) + cross[2].powi(2)).sqrt()
+}
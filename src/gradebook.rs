@@ -0,0 +1,334 @@
+use crate::{
+    authentication, models,
+    schema::{
+        answers::dsl as answers_dsl, attempts::dsl as attempts_dsl,
+        labelsets::dsl as labelsets_dsl, models::dsl as models_dsl,
+        questions::dsl as questions_dsl, quizzes::dsl as quizzes_dsl, users::dsl as users_dsl,
+    },
+    util, MainDbConn,
+};
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl, SqliteConnection};
+use rocket::{get, http::ContentType, response::Content, FromFormValue};
+use rocket_contrib::uuid::Uuid;
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    io::{Cursor, Write},
+};
+
+#[derive(FromFormValue, Debug, Clone, Copy)]
+pub enum ExportFormat {
+    #[form(value = "csv")]
+    Csv,
+    #[form(value = "xlsx")]
+    Xlsx,
+}
+
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Empty,
+}
+
+/// A table with a header row, which can be written as CSV or as an XLSX workbook.
+pub struct Sheet {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Sheet {
+    /// Writes the sheet following RFC 4180.
+    pub fn to_csv(&self) -> String {
+        fn field(s: &str) -> String {
+            if s.contains(&[',', '"', '\n', '\r'][..]) {
+                format!("\"{}\"", s.replace('"', "\"\""))
+            } else {
+                s.to_owned()
+            }
+        }
+
+        let mut csv = String::new();
+        let header = self.header.iter().map(|h| field(h));
+        let rows = self.rows.iter().map(|row| {
+            row.iter()
+                .map(|cell| match cell {
+                    Cell::Text(s) => field(s),
+                    Cell::Number(n) => n.to_string(),
+                    Cell::Empty => String::new(),
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+        csv.push_str(&header.collect::<Vec<_>>().join(","));
+        csv.push_str("\r\n");
+        for row in rows {
+            csv.push_str(&row);
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+
+    /// Writes the sheet as a workbook with a single worksheet, with the bare minimum of parts
+    /// that spreadsheet applications require.
+    pub fn to_xlsx(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#;
+        const RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+        const WORKBOOK: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Gradebook" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+        const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#;
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        let parts = [
+            ("[Content_Types].xml", CONTENT_TYPES.to_owned()),
+            ("_rels/.rels", RELS.to_owned()),
+            ("xl/workbook.xml", WORKBOOK.to_owned()),
+            ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS.to_owned()),
+            ("xl/worksheets/sheet1.xml", self.worksheet_xml()),
+        ];
+        for (name, content) in parts.iter() {
+            zip.start_file(*name, options)?;
+            zip.write_all(content.as_bytes())?;
+        }
+        Ok(zip.finish()?.into_inner())
+    }
+
+    fn worksheet_xml(&self) -> String {
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#,
+        );
+        let header = self.header.iter().map(|h| Cell::Text(h.clone())).collect();
+        for (r, row) in std::iter::once(&header).chain(self.rows.iter()).enumerate() {
+            xml.push_str(&format!(r#"<row r="{}">"#, r + 1));
+            for (c, cell) in row.iter().enumerate() {
+                let reference = format!("{}{}", column_name(c), r + 1);
+                match cell {
+                    Cell::Text(s) => xml.push_str(&format!(
+                        r#"<c r="{}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                        reference,
+                        util::escape_xml(s)
+                    )),
+                    Cell::Number(n) if n.is_finite() => {
+                        xml.push_str(&format!(r#"<c r="{}"><v>{}</v></c>"#, reference, n))
+                    }
+                    _ => {}
+                }
+            }
+            xml.push_str("</row>");
+        }
+        xml.push_str("</sheetData></worksheet>");
+        xml
+    }
+}
+
+/// The spreadsheet name of a zero-based column index, such as `A`, `Z` or `AA`.
+fn column_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// The columns a quiz adds to the gradebook, and the cells of each student by user ID.
+struct QuizColumns {
+    header: Vec<String>,
+    students: HashMap<i32, Vec<Cell>>,
+}
+
+/// The number of submitted attempts, the best and the last score, and the score of each question
+/// in the best attempt. Questions that weren't drawn for the best attempt are left empty. With
+/// `prefix`, the headers start with the name of the quiz.
+fn quiz_columns(
+    conn: &SqliteConnection,
+    quiz: &models::Quiz,
+    prefix: bool,
+) -> Result<QuizColumns, Box<dyn Error>> {
+    let questions = questions_dsl::questions
+        .filter(questions_dsl::quiz.eq(&quiz.id))
+        .order((questions_dsl::position, questions_dsl::id))
+        .load::<models::Question>(conn)?;
+    let attempts = attempts_dsl::attempts
+        .filter(attempts_dsl::quiz.eq(&quiz.id))
+        .filter(attempts_dsl::submittedat.is_not_null())
+        .order((attempts_dsl::submittedat, attempts_dsl::id))
+        .load::<models::Attempt>(conn)?;
+    let attempt_ids: Vec<i32> = attempts.iter().map(|a| a.id).collect();
+    let answers = answers_dsl::answers
+        .filter(answers_dsl::attempt.eq_any(&attempt_ids))
+        .load::<models::Answer>(conn)?;
+
+    let mut header = vec![
+        "Attempts".to_owned(),
+        "Best score".to_owned(),
+        "Last score".to_owned(),
+    ];
+    header.extend(
+        questions
+            .iter()
+            .enumerate()
+            .map(|(i, q)| format!("Q{}: {}", i + 1, q.textprompt)),
+    );
+    if prefix {
+        header = header
+            .into_iter()
+            .map(|h| format!("{}: {}", quiz.name, h))
+            .collect();
+    }
+
+    let mut by_student: BTreeMap<i32, Vec<&models::Attempt>> = BTreeMap::new();
+    for attempt in &attempts {
+        // Guests have no row of their own.
+        if let Some(student) = attempt.userid {
+            by_student.entry(student).or_default().push(attempt);
+        }
+    }
+
+    let students = by_student
+        .into_iter()
+        .map(|(student, attempts)| {
+            let score = |a: &models::Attempt| a.score.unwrap_or(0.0);
+            // Attempts are in order of submission, so later attempts win ties.
+            let best = attempts
+                .iter()
+                .copied()
+                .fold(None, |best: Option<&models::Attempt>, a| match best {
+                    Some(b) if score(b) > score(a) => Some(b),
+                    _ => Some(a),
+                });
+            let last = attempts.last().copied();
+
+            let mut cells = vec![
+                Cell::Number(attempts.len() as f64),
+                best.map_or(Cell::Empty, |a| Cell::Number(score(a))),
+                last.map_or(Cell::Empty, |a| Cell::Number(score(a))),
+            ];
+            cells.extend(questions.iter().map(|q| {
+                answers
+                    .iter()
+                    .find(|answer| {
+                        Some(answer.attempt) == best.map(|a| a.id) && answer.question == q.id
+                    })
+                    .map_or(Cell::Empty, |answer| Cell::Number(answer.score))
+            }));
+            (student, cells)
+        })
+        .collect();
+
+    Ok(QuizColumns { header, students })
+}
+
+/// Puts the columns of the quizzes side by side, with a row for each student with an attempt
+/// at any of them.
+fn to_sheet(conn: &SqliteConnection, quizzes: Vec<QuizColumns>) -> Result<Sheet, Box<dyn Error>> {
+    let mut student_ids: Vec<i32> = quizzes
+        .iter()
+        .flat_map(|q| q.students.keys().copied())
+        .collect();
+    student_ids.sort_unstable();
+    student_ids.dedup();
+
+    let mut students: Vec<(i32, String)> = users_dsl::users
+        .select((users_dsl::id, users_dsl::username))
+        .filter(users_dsl::id.eq_any(&student_ids))
+        .load(conn)?;
+    students.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let mut header = vec!["Username".to_owned()];
+    header.extend(quizzes.iter().flat_map(|q| q.header.iter().cloned()));
+
+    let mut quizzes = quizzes;
+    let rows = students
+        .into_iter()
+        .map(|(id, username)| {
+            let mut row = vec![Cell::Text(username)];
+            for quiz in quizzes.iter_mut() {
+                match quiz.students.remove(&id) {
+                    Some(cells) => row.extend(cells),
+                    None => row.extend(quiz.header.iter().map(|_| Cell::Empty)),
+                }
+            }
+            row
+        })
+        .collect();
+
+    Ok(Sheet { header, rows })
+}
+
+fn export(sheet: Sheet, format: Option<ExportFormat>) -> Result<Content<Vec<u8>>, Box<dyn Error>> {
+    Ok(match format.unwrap_or(ExportFormat::Csv) {
+        ExportFormat::Csv => Content(ContentType::CSV, sheet.to_csv().into_bytes()),
+        ExportFormat::Xlsx => Content(
+            ContentType::new(
+                "application",
+                "vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ),
+            sheet.to_xlsx()?,
+        ),
+    })
+}
+
+/// Exports the scores of every student that has submitted an attempt at a quiz.
+#[get("/quiz/<uuid>?<format>")]
+pub fn quiz(
+    _auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+    format: Option<ExportFormat>,
+) -> Result<Option<Content<Vec<u8>>>, Box<dyn Error>> {
+    let quiz = quizzes_dsl::quizzes
+        .filter(quizzes_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<models::Quiz>(&*conn)?
+        .pop();
+    let quiz = match quiz {
+        Some(q) => q,
+        None => return Ok(None),
+    };
+
+    let columns = quiz_columns(&*conn, &quiz, false)?;
+    let sheet = to_sheet(&*conn, vec![columns])?;
+    export(sheet, format).map(Some)
+}
+
+/// Exports the scores of every quiz on the models of a group, with the columns of each quiz
+/// prefixed by its name.
+#[get("/group/<group>?<format>")]
+pub fn group(
+    _auth: authentication::Moderator,
+    conn: MainDbConn,
+    group: String,
+    format: Option<ExportFormat>,
+) -> Result<Option<Content<Vec<u8>>>, Box<dyn Error>> {
+    let model_ids: Vec<i32> = models_dsl::models
+        .select(models_dsl::id)
+        .filter(models_dsl::category.eq(&group))
+        .load(&*conn)?;
+    let labelset_ids: Vec<i32> = labelsets_dsl::labelsets
+        .select(labelsets_dsl::id)
+        .filter(labelsets_dsl::model.eq_any(&model_ids))
+        .load(&*conn)?;
+    let quizzes = quizzes_dsl::quizzes
+        .filter(quizzes_dsl::labelset.eq_any(&labelset_ids))
+        .order((quizzes_dsl::name, quizzes_dsl::id))
+        .load::<models::Quiz>(&*conn)?;
+    if quizzes.is_empty() {
+        return Ok(None);
+    }
+
+    let columns = quizzes
+        .iter()
+        .map(|quiz| quiz_columns(&*conn, quiz, true))
+        .collect::<Result<Vec<_>, _>>()?;
+    let sheet = to_sheet(&*conn, columns)?;
+    export(sheet, format).map(Some)
+}
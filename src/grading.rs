@@ -0,0 +1,322 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Words left out when comparing answers, so that "m. biceps brachii" and "Biceps brachii
+/// muscle" are the same answer. An answer made up of nothing else keeps them.
+const FILLER_WORDS: &[&str] = &["the", "m", "mm", "musculus", "musculi", "muscle", "muscles"];
+
+/// Letters with diacritics, each followed by the letter they're compared as.
+const DIACRITICS: &[(&str, char)] = &[
+    ("àáâãäåāăą", 'a'),
+    ("çćĉċč", 'c'),
+    ("ďđ", 'd'),
+    ("èéêëēĕėęě", 'e'),
+    ("ĝğġģ", 'g'),
+    ("ĥħ", 'h'),
+    ("ìíîïĩīĭįı", 'i'),
+    ("ĵ", 'j'),
+    ("ķ", 'k'),
+    ("ĺļľŀł", 'l'),
+    ("ñńņňŉ", 'n'),
+    ("òóôõöøōŏő", 'o'),
+    ("ŕŗř", 'r'),
+    ("śŝşš", 's'),
+    ("ţťŧ", 't'),
+    ("ùúûüũūŭůűų", 'u'),
+    ("ŵ", 'w'),
+    ("ýÿŷ", 'y'),
+    ("źżž", 'z'),
+];
+
+/// How a written answer compared to the accepted answers.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Verdict {
+    Exact,
+    /// Close enough to an accepted answer to be a misspelling of it.
+    AcceptedWithTypo,
+    Wrong,
+}
+
+impl Verdict {
+    const EXACT: i16 = 0;
+    const ACCEPTED_WITH_TYPO: i16 = 1;
+    const WRONG: i16 = 2;
+
+    pub fn to_db(self) -> i16 {
+        match self {
+            Self::Exact => Self::EXACT,
+            Self::AcceptedWithTypo => Self::ACCEPTED_WITH_TYPO,
+            Self::Wrong => Self::WRONG,
+        }
+    }
+
+    pub fn from_db(verdict: i16) -> Option<Self> {
+        match verdict {
+            Self::EXACT => Some(Self::Exact),
+            Self::ACCEPTED_WITH_TYPO => Some(Self::AcceptedWithTypo),
+            Self::WRONG => Some(Self::Wrong),
+            _ => None,
+        }
+    }
+
+    pub fn is_correct(self) -> bool {
+        self != Self::Wrong
+    }
+}
+
+/// Reduces an answer to what matters when comparing it: lowercase letters and digits without
+/// diacritics, with single spaces between words and without filler words.
+pub fn normalise(answer: &str) -> String {
+    let mut folded = String::with_capacity(answer.len());
+    for c in answer.chars().flat_map(char::to_lowercase) {
+        match c {
+            'ß' => folded.push_str("ss"),
+            'æ' => folded.push_str("ae"),
+            'œ' => folded.push_str("oe"),
+            c if c.is_alphanumeric() => folded.push(
+                DIACRITICS
+                    .iter()
+                    .find(|(letters, _)| letters.contains(c))
+                    .map_or(c, |(_, base)| *base),
+            ),
+            _ => folded.push(' '),
+        }
+    }
+
+    let words: Vec<&str> = folded.split_whitespace().collect();
+    let meaningful: Vec<&str> = words
+        .iter()
+        .copied()
+        .filter(|w| !FILLER_WORDS.contains(w))
+        .collect();
+    if meaningful.is_empty() {
+        words.join(" ")
+    } else {
+        meaningful.join(" ")
+    }
+}
+
+/// The number of insertions, deletions, substitutions and swaps of adjacent letters needed to
+/// turn one string into the other.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Three rows of the optimal string alignment distance matrix.
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// How many typos an answer of the given length may have when no tolerance is set: one for every
+/// five letters, up to three.
+pub fn default_tolerance(length: usize) -> usize {
+    (length / 5).min(3)
+}
+
+/// Compares a written answer to the accepted answers. Answers are compared normalised, and may
+/// be off by `tolerance` typos, or by `default_tolerance` if it's not set. An answer that is
+/// exactly one of the `rejected` answers is wrong even if it's close to an accepted one, so that
+/// naming a neighbouring structure with a similar name isn't taken for a typo.
+pub fn grade_text(
+    given: &str,
+    accepted: &[&str],
+    rejected: &[&str],
+    tolerance: Option<usize>,
+) -> Verdict {
+    let given = normalise(given);
+    if given.is_empty() {
+        return Verdict::Wrong;
+    }
+    let accepted: Vec<String> = accepted.iter().map(|a| normalise(a)).collect();
+    if accepted.contains(&given) {
+        return Verdict::Exact;
+    }
+    if rejected.iter().any(|r| normalise(r) == given) {
+        return Verdict::Wrong;
+    }
+
+    let close = accepted.iter().filter(|a| !a.is_empty()).any(|a| {
+        let allowed = tolerance.unwrap_or_else(|| default_tolerance(a.chars().count()));
+        edit_distance(a, &given) <= allowed
+    });
+    if close {
+        Verdict::AcceptedWithTypo
+    } else {
+        Verdict::Wrong
+    }
+}
+
+/// The overlap needed for full marks on a painted region when the question doesn't set one.
+pub const DEFAULT_PASS_THRESHOLD: f64 = 0.75;
+
+/// How the overlap between a painted region and the target label is measured.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum OverlapMeasure {
+    /// The shared vertices over the vertices in either region.
+    Jaccard,
+    /// Twice the shared vertices over the sum of the sizes of the regions, which is more lenient.
+    Dice,
+}
+
+impl OverlapMeasure {
+    const JACCARD: i16 = 0;
+    const DICE: i16 = 1;
+
+    pub fn to_db(self) -> i16 {
+        match self {
+            Self::Jaccard => Self::JACCARD,
+            Self::Dice => Self::DICE,
+        }
+    }
+
+    pub fn from_db(measure: i16) -> Option<Self> {
+        match measure {
+            Self::JACCARD => Some(Self::Jaccard),
+            Self::DICE => Some(Self::Dice),
+            _ => None,
+        }
+    }
+}
+
+/// How well a painted region matched the target label.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonRegionScore {
+    /// From 0 to 1, by the measure of the question.
+    pub overlap: f64,
+    /// Whether the overlap reached the pass threshold.
+    pub passed: bool,
+    /// Vertices of the target that weren't painted.
+    pub missed: Vec<usize>,
+    /// Painted vertices outside the target.
+    pub extra: Vec<usize>,
+}
+
+/// Scores a painted region against the vertices of the target label. Reaching the pass
+/// threshold gives full marks, and anything less gives credit in proportion.
+pub fn score_region(
+    painted: &[usize],
+    target: &[usize],
+    measure: OverlapMeasure,
+    pass_threshold: f64,
+) -> (f64, JsonRegionScore) {
+    let painted: BTreeSet<usize> = painted.iter().copied().collect();
+    let target: BTreeSet<usize> = target.iter().copied().collect();
+    let shared = painted.intersection(&target).count() as f64;
+    let overlap = match measure {
+        OverlapMeasure::Jaccard => {
+            let union = painted.union(&target).count() as f64;
+            if union > 0.0 {
+                shared / union
+            } else {
+                0.0
+            }
+        }
+        OverlapMeasure::Dice => {
+            let sizes = (painted.len() + target.len()) as f64;
+            if sizes > 0.0 {
+                2.0 * shared / sizes
+            } else {
+                0.0
+            }
+        }
+    };
+
+    let passed = overlap >= pass_threshold;
+    let score = if passed {
+        1.0
+    } else {
+        overlap / pass_threshold
+    };
+    (
+        score,
+        JsonRegionScore {
+            overlap,
+            passed,
+            missed: target.difference(&painted).copied().collect(),
+            extra: painted.difference(&target).copied().collect(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalise_drops_filler_words_and_punctuation() {
+        assert_eq!(normalise("m. biceps brachii"), "biceps brachii");
+        assert_eq!(normalise("Biceps brachii muscle"), "biceps brachii");
+        assert_eq!(normalise("  The   muscle "), "the muscle");
+    }
+
+    #[test]
+    fn normalise_folds_diacritics() {
+        assert_eq!(normalise("Trapézius"), "trapezius");
+        assert_eq!(normalise("Muskel Straße"), "muskel strasse");
+        assert_eq!(normalise("Ærø"), "aero");
+    }
+
+    #[test]
+    fn edit_distance_counts_swaps_once() {
+        assert_eq!(edit_distance("biceps", "biceps"), 0);
+        assert_eq!(edit_distance("biceps", "bicpes"), 1);
+        assert_eq!(edit_distance("biceps", "triceps"), 2);
+        assert_eq!(edit_distance("", "ulna"), 4);
+    }
+
+    #[test]
+    fn same_answer_in_other_words_is_exact() {
+        let verdict = grade_text("m. biceps brachii", &["Biceps brachii muscle"], &[], None);
+        assert_eq!(verdict, Verdict::Exact);
+    }
+
+    #[test]
+    fn diacritics_are_exact() {
+        let verdict = grade_text("musculus trapezius", &["Trapézius"], &[], None);
+        assert_eq!(verdict, Verdict::Exact);
+    }
+
+    #[test]
+    fn swapped_letters_are_a_typo() {
+        let verdict = grade_text("bicpes brachii", &["Biceps brachii"], &[], None);
+        assert_eq!(verdict, Verdict::AcceptedWithTypo);
+    }
+
+    #[test]
+    fn too_many_typos_are_wrong() {
+        let verdict = grade_text("bicpes brachii", &["Biceps brachii"], &[], Some(0));
+        assert_eq!(verdict, Verdict::Wrong);
+    }
+
+    #[test]
+    fn rejected_neighbour_within_tolerance_is_wrong() {
+        assert!(edit_distance("teres minor", "teres major") <= default_tolerance(11));
+        let verdict = grade_text("Teres minor", &["Teres major"], &["Teres minor"], None);
+        assert_eq!(verdict, Verdict::Wrong);
+        let verdict = grade_text("Teres minor", &["Teres major"], &[], None);
+        assert_eq!(verdict, Verdict::AcceptedWithTypo);
+    }
+
+    #[test]
+    fn empty_answer_is_wrong() {
+        assert_eq!(grade_text(" . ", &["Ulna"], &[], None), Verdict::Wrong);
+    }
+}
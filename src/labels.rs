@@ -1,13 +1,25 @@
 use crate::{
+    analysis::{self, JsonLabelSetAnalysis, JsonOverlap},
     authentication,
-    models::{NewLabel, NewLabelSet},
-    util, MainDbConn,
+    colour::{self, Colour},
+    geometry::{self, LabelGeometry, Mesh},
+    listing::{JsonPage, ListQuery, SortOrder},
+    media,
+    models::{self, NewLabel, NewLabelSet},
+    publication::{self, Publication},
+    search, sharing, util, MainDbConn,
 };
-use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
-use rocket::{delete, get, post, put};
+use diesel::{
+    expression_methods::EscapeExpressionMethods, ExpressionMethods, QueryDsl, QueryResult,
+    RunQueryDsl, SqliteConnection, TextExpressionMethods,
+};
+use rocket::{delete, get, http::Status, post, put, request::Form, response::status};
 use rocket_contrib::{json::Json, uuid::Uuid};
 use serde::{Deserialize, Serialize};
-use std::error::Error;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    error::Error,
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -16,16 +28,20 @@ pub struct JsonLabelSet {
     pub uuid: Option<String>,
     pub name: String,
     pub model: i32,
+    /// Left out to keep the status of a saved set. New sets start out as drafts.
+    #[serde(default)]
+    pub status: Option<Publication>,
     pub labels: Vec<JsonLabel>,
 }
 
 impl JsonLabelSet {
-    fn from_db(set: crate::models::LabelSet, labels: Vec<crate::models::Label>) -> Self {
+    pub fn from_db(set: crate::models::LabelSet, labels: Vec<crate::models::Label>) -> Self {
         Self {
             id: Some(set.id),
             uuid: Some(set.uuid),
             name: set.name,
             model: set.model,
+            status: Some(Publication::from_db(set.status)),
             labels: labels.into_iter().map(From::from).collect(),
         }
     }
@@ -39,25 +55,61 @@ impl JsonLabelSet {
             },
             name: self.name.as_ref(),
             model: self.model,
+            owner: None,
+            status: self.status.unwrap_or(Publication::Draft).to_db(),
             uuid,
         }
     }
 }
 
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonLabelSetSummary {
+    pub id: i32,
+    pub uuid: String,
+    pub name: String,
+    pub model: i32,
+    pub owner: Option<i32>,
+    pub status: Publication,
+}
+
+impl From<crate::models::LabelSet> for JsonLabelSetSummary {
+    fn from(set: crate::models::LabelSet) -> Self {
+        Self {
+            id: set.id,
+            uuid: set.uuid,
+            name: set.name,
+            model: set.model,
+            owner: set.owner,
+            status: Publication::from_db(set.status),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct JsonLabel {
-    pub colour: String,
+    pub colour: Colour,
     pub name: String,
+    /// Other names accepted when the label is to be named.
+    #[serde(default)]
+    pub synonyms: Vec<String>,
     pub vertices: String,
+    /// Computed from the model when the label set is saved.
+    #[serde(default, skip_deserializing)]
+    pub geometry: Option<LabelGeometry>,
 }
 
 impl From<crate::models::Label> for JsonLabel {
     fn from(l: crate::models::Label) -> Self {
+        let geometry = geometry_from_db(&l);
         Self {
+            geometry,
+            synonyms: util::split_lines(l.synonyms.as_deref()),
             name: l.name,
             vertices: String::from_utf8(l.vertices).unwrap(),
-            colour: l.colour,
+            // Labels saved before colours were validated may contain anything.
+            colour: l.colour.parse().unwrap_or_default(),
         }
     }
 }
@@ -65,59 +117,310 @@ impl From<crate::models::Label> for JsonLabel {
 impl<'a> From<&'a JsonLabel> for crate::models::NewLabel<'a> {
     fn from(p: &'a JsonLabel) -> Self {
         Self {
-            colour: p.colour.as_ref(),
+            colour: p.colour.to_string(),
             labelset: Default::default(),
             name: p.name.as_ref(),
             vertices: p.vertices.as_bytes(),
+            vertexcount: None,
+            area: None,
+            centroidx: None,
+            centroidy: None,
+            centroidz: None,
+            minx: None,
+            miny: None,
+            minz: None,
+            maxx: None,
+            maxy: None,
+            maxz: None,
+            synonyms: util::join_lines(&p.synonyms),
+        }
+    }
+}
+
+fn geometry_from_db(l: &crate::models::Label) -> Option<LabelGeometry> {
+    Some(LabelGeometry {
+        vertex_count: l.vertexcount?,
+        area: l.area?,
+        centroid: [l.centroidx?, l.centroidy?, l.centroidz?],
+        min: [l.minx?, l.miny?, l.minz?],
+        max: [l.maxx?, l.maxy?, l.maxz?],
+    })
+}
+
+fn label_vertices(labels: &[JsonLabel]) -> Vec<(&str, Vec<usize>)> {
+    labels
+        .iter()
+        .map(|l| (l.name.as_ref(), geometry::parse_vertices(&l.vertices)))
+        .collect()
+}
+
+fn set_geometry(label: &mut NewLabel, geometry: LabelGeometry) {
+    let LabelGeometry {
+        vertex_count,
+        area,
+        centroid,
+        min,
+        max,
+    } = geometry;
+    label.vertexcount = Some(vertex_count);
+    label.area = Some(area);
+    label.centroidx = Some(centroid[0]);
+    label.centroidy = Some(centroid[1]);
+    label.centroidz = Some(centroid[2]);
+    label.minx = Some(min[0]);
+    label.miny = Some(min[1]);
+    label.minz = Some(min[2]);
+    label.maxx = Some(max[0]);
+    label.maxy = Some(max[1]);
+    label.maxz = Some(max[2]);
+}
+
+/// A quiz with questions referring to labels that a save would remove.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonOrphanedQuiz {
+    pub uuid: String,
+    pub name: String,
+    pub missing_labels: Vec<String>,
+}
+
+/// Why a label set couldn't be saved.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonSaveConflict {
+    pub overlaps: Vec<JsonOverlap>,
+    pub orphaned_quizzes: Vec<JsonOrphanedQuiz>,
+}
+
+/// Either the UUID of the saved set, or what stopped it from being saved.
+pub type SaveResult = Result<Json<String>, status::Conflict<Json<JsonSaveConflict>>>;
+
+/// The label references of the quizzes on a label set. Labels get new IDs every time their set
+/// is saved, so the references are kept by label name and carried over to the new labels.
+#[derive(Default)]
+struct LabelReferences {
+    quizzes: Vec<models::Quiz>,
+    /// Question ID, quiz ID and label name.
+    questions: Vec<(i32, i32, String)>,
+    /// Option ID, quiz ID and label name.
+    options: Vec<(i32, i32, String)>,
+    /// Question ID, quiz ID and the names of the labels highlighted in its feedback.
+    highlights: Vec<(i32, i32, Vec<String>)>,
+}
+
+impl LabelReferences {
+    /// Loads the references to the labels currently in the set. References to labels outside
+    /// the set are already broken, and are left alone.
+    fn load(conn: &SqliteConnection, set_id: i32) -> QueryResult<Self> {
+        use crate::schema::labels::dsl as labels_dsl;
+        use crate::schema::questionoptions::dsl as options_dsl;
+        use crate::schema::questions::dsl as questions_dsl;
+        use crate::schema::quizzes::dsl as quizzes_dsl;
+
+        let names: HashMap<i32, String> = labels_dsl::labels
+            .select((labels_dsl::id, labels_dsl::name))
+            .filter(labels_dsl::labelset.eq(&set_id))
+            .load(conn)?
+            .into_iter()
+            .collect();
+        let quizzes = quizzes_dsl::quizzes
+            .filter(quizzes_dsl::labelset.eq(&set_id))
+            .load::<models::Quiz>(conn)?;
+        let questions = questions_dsl::questions
+            .filter(questions_dsl::quiz.eq_any(quizzes.iter().map(|q| q.id).collect::<Vec<_>>()))
+            .load::<models::Question>(conn)?;
+        let options = options_dsl::questionoptions
+            .filter(
+                options_dsl::question.eq_any(questions.iter().map(|q| q.id).collect::<Vec<_>>()),
+            )
+            .load::<models::QuestionOption>(conn)?;
+
+        let quiz_of: HashMap<i32, i32> = questions.iter().map(|q| (q.id, q.quiz)).collect();
+        let name_of = |label: Option<i32>| label.and_then(|l| names.get(&l)).cloned();
+        Ok(Self {
+            questions: questions
+                .iter()
+                .filter_map(|q| name_of(q.label).map(|name| (q.id, q.quiz, name)))
+                .collect(),
+            options: options
+                .iter()
+                .filter_map(|o| name_of(o.label).map(|name| (o.id, quiz_of[&o.question], name)))
+                .collect(),
+            highlights: questions
+                .iter()
+                .filter_map(|q| {
+                    let names: Vec<String> = q
+                        .highlightlabels
+                        .as_deref()?
+                        .split(',')
+                        .filter_map(|l| name_of(l.parse().ok()))
+                        .collect();
+                    if names.is_empty() {
+                        None
+                    } else {
+                        Some((q.id, q.quiz, names))
+                    }
+                })
+                .collect(),
+            quizzes,
+        })
+    }
+
+    /// Lists the quizzes referring to labels that aren't among `labels`.
+    fn orphaned(&self, labels: &[JsonLabel]) -> Vec<JsonOrphanedQuiz> {
+        let names: HashSet<&str> = labels.iter().map(|l| l.name.as_ref()).collect();
+        self.quizzes
+            .iter()
+            .filter_map(|quiz| {
+                let highlighted = self
+                    .highlights
+                    .iter()
+                    .flat_map(|(_, q, labels)| labels.iter().map(move |name| (q, name)));
+                let missing: BTreeSet<&str> = self
+                    .questions
+                    .iter()
+                    .chain(self.options.iter())
+                    .map(|(_, q, name)| (q, name))
+                    .chain(highlighted)
+                    .filter(|(q, name)| **q == quiz.id && !names.contains(name.as_str()))
+                    .map(|(_, name)| name.as_str())
+                    .collect();
+                if missing.is_empty() {
+                    return None;
+                }
+                Some(JsonOrphanedQuiz {
+                    uuid: quiz.uuid.clone(),
+                    name: quiz.name.clone(),
+                    missing_labels: missing.into_iter().map(str::to_owned).collect(),
+                })
+            })
+            .collect()
+    }
+
+    /// Points the references at the labels of the same name in the saved set. Where names are
+    /// repeated, the first label is used.
+    fn remap(&self, conn: &SqliteConnection, set_id: i32) -> QueryResult<()> {
+        use crate::schema::labels::dsl as labels_dsl;
+        use crate::schema::questionoptions::dsl as options_dsl;
+        use crate::schema::questions::dsl as questions_dsl;
+
+        let labels: Vec<(i32, String)> = labels_dsl::labels
+            .select((labels_dsl::id, labels_dsl::name))
+            .filter(labels_dsl::labelset.eq(&set_id))
+            .order(labels_dsl::id)
+            .load(conn)?;
+        let mut ids: HashMap<&str, i32> = HashMap::new();
+        for (id, name) in &labels {
+            ids.entry(name.as_str()).or_insert(*id);
+        }
+
+        // Rows are updated one by one, as the new IDs may reuse those of the old labels.
+        for (question, _, name) in &self.questions {
+            rocket_contrib::databases::diesel::update(questions_dsl::questions.find(question))
+                .set(questions_dsl::label.eq(ids.get(name.as_str())))
+                .execute(conn)?;
+        }
+        for (option, _, name) in &self.options {
+            rocket_contrib::databases::diesel::update(options_dsl::questionoptions.find(option))
+                .set(options_dsl::label.eq(ids.get(name.as_str())))
+                .execute(conn)?;
         }
+        // Highlighted labels that are gone are left out.
+        for (question, _, names) in &self.highlights {
+            let highlighted: Vec<String> = names
+                .iter()
+                .filter_map(|name| ids.get(name.as_str()))
+                .map(|id| id.to_string())
+                .collect();
+            let highlighted = Some(highlighted.join(",")).filter(|h| !h.is_empty());
+            rocket_contrib::databases::diesel::update(questions_dsl::questions.find(question))
+                .set(questions_dsl::highlightlabels.eq(highlighted))
+                .execute(conn)?;
+        }
+
+        Ok(())
     }
 }
 
-#[post("/", format = "json", data = "<data>")]
+#[post("/?<strict>", format = "json", data = "<data>")]
 pub fn create(
     auth: authentication::Moderator,
     conn: MainDbConn,
+    strict: Option<bool>,
     data: Json<JsonLabelSet>,
-) -> Result<Json<String>, Box<dyn Error>> {
+) -> Result<SaveResult, Box<dyn Error>> {
     let mut data = data.into_inner();
     data.id = None; // Prerequisite to avoid an "insert".
-    add(auth, conn, util::create_uuid(), data)
+    add(
+        auth,
+        conn,
+        util::create_uuid(),
+        data,
+        strict.unwrap_or(false),
+    )
 }
 
-#[put("/<uuid>", format = "json", data = "<data>")]
+#[put("/<uuid>?<strict>", format = "json", data = "<data>")]
 pub fn put(
     auth: authentication::Moderator,
     conn: MainDbConn,
     uuid: Uuid,
+    strict: Option<bool>,
     data: Json<JsonLabelSet>,
-) -> Result<Json<String>, Box<dyn Error>> {
-    add(auth, conn, uuid, data.into_inner())
+) -> Result<SaveResult, Box<dyn Error>> {
+    add(auth, conn, uuid, data.into_inner(), strict.unwrap_or(false))
 }
 
+/// Saves a label set. In strict mode, the set is rejected if any two labels share vertices.
 pub fn add(
-    _auth: authentication::Moderator,
+    auth: authentication::Moderator,
     conn: MainDbConn,
     uuid: Uuid,
     data: JsonLabelSet,
-) -> Result<Json<String>, Box<dyn Error>> {
+    strict: bool,
+) -> Result<SaveResult, Box<dyn Error>> {
     use crate::schema::labels::dsl::{self as labels_dsl, labels};
     use crate::schema::labelsets::dsl::{self as labelsets_dsl, labelsets};
 
+    if strict {
+        let overlapping = analysis::overlaps(&label_vertices(&data.labels));
+        if !overlapping.is_empty() {
+            return Ok(Err(status::Conflict(Some(Json(JsonSaveConflict {
+                overlaps: overlapping,
+                ..Default::default()
+            })))));
+        }
+    }
+
     let uuid = (&uuid).to_string();
     let mut new_set = data.to_new_label_set(uuid.as_ref());
     let mut new_labels: Vec<_> = data.labels.iter().map(NewLabel::from).collect();
 
-    // Check if it's already in the database, and if so, use it's ID.
-    let set_id = data.id.or_else(|| {
-        labelsets_dsl::labelsets
-            .filter(labelsets_dsl::uuid.eq(&uuid))
-            .load::<crate::models::LabelSet>(&*conn)
-            .ok()
-            .map(|mut sets| sets.pop().map(|set| set.id))
-            .flatten()
-    });
+    // Check if it's already in the database, and if so, use it's ID and keep its owner.
+    let previous = labelsets_dsl::labelsets
+        .filter(labelsets_dsl::uuid.eq(&uuid))
+        .load::<crate::models::LabelSet>(&*conn)?
+        .pop();
+    let set_id = data.id.or_else(|| previous.as_ref().map(|set| set.id));
     new_set.id = set_id;
 
+    // Quizzes can't be left referring to labels that no longer exist.
+    let references = match set_id {
+        Some(id) => LabelReferences::load(&*conn, id)?,
+        None => LabelReferences::default(),
+    };
+    let orphaned = references.orphaned(&data.labels);
+    if !orphaned.is_empty() {
+        return Ok(Err(status::Conflict(Some(Json(JsonSaveConflict {
+            orphaned_quizzes: orphaned,
+            ..Default::default()
+        })))));
+    }
+    if let (None, Some(previous)) = (data.status, &previous) {
+        new_set.status = previous.status;
+    }
+    new_set.owner = previous.and_then(|set| set.owner).or(Some(auth.0.id));
+
     rocket_contrib::databases::diesel::replace_into(labelsets)
         .values(&new_set)
         .execute(&*conn)?;
@@ -136,19 +439,91 @@ pub fn add(
 
     new_labels.iter_mut().for_each(|l| l.labelset = set_id);
 
+    // The geometry is only a convenience for the viewer, so a model that can't be read shouldn't
+    // stop the labels from being saved.
+    let mesh = crate::schema::models::dsl::models
+        .find(data.model)
+        .load::<crate::models::Model>(&*conn)?
+        .pop()
+        .and_then(|model| Mesh::load(&model.filename).ok());
+    if let Some(mesh) = mesh {
+        for (new_label, label) in new_labels.iter_mut().zip(data.labels.iter()) {
+            if let Some(g) = mesh.summarise(&geometry::parse_vertices(&label.vertices)) {
+                set_geometry(new_label, g);
+            }
+        }
+    }
+
     rocket_contrib::databases::diesel::delete(labels)
         .filter(labels_dsl::labelset.eq(&set_id))
         .execute(&*conn)?;
     rocket_contrib::databases::diesel::insert_into(labels)
         .values(&new_labels)
         .execute(&*conn)?;
+    references.remap(&*conn, set_id)?;
+    let names: Vec<&str> = data.labels.iter().map(|l| l.name.as_ref()).collect();
+    media::retain_for_labels(&*conn, set_id, &names)?;
+
+    search::index_labelset(
+        &*conn,
+        &uuid,
+        &data.name,
+        data.labels
+            .iter()
+            .map(|l| (l.name.as_ref(), l.synonyms.as_slice())),
+    )?;
 
-    Ok(Json(uuid))
+    Ok(Ok(Json(uuid)))
+}
+
+#[get("/?<query..>")]
+pub fn list(
+    auth: &authentication::User,
+    conn: MainDbConn,
+    query: Form<ListQuery>,
+) -> Result<Json<JsonPage<JsonLabelSetSummary>>, Box<dyn Error>> {
+    use crate::schema::labelsets::dsl as labelsets_dsl;
+
+    let model_ids = query.model_ids(&*conn)?;
+    let name_pattern = query.name_pattern();
+    let statuses = publication::visible_statuses(&auth.0, query.status);
+
+    // Boxed queries can't be cloned, so the filters are applied once for counting and once more
+    // for fetching the page.
+    let filtered = || {
+        let mut sets = labelsets_dsl::labelsets
+            .filter(labelsets_dsl::status.eq_any(statuses.clone()))
+            .into_boxed();
+        if let Some(ids) = &model_ids {
+            sets = sets.filter(labelsets_dsl::model.eq_any(ids.clone()));
+        }
+        if let Some(pattern) = &name_pattern {
+            sets = sets.filter(labelsets_dsl::name.like(pattern.clone()).escape('\\'));
+        }
+        if let Some(owner) = query.owner {
+            sets = sets.filter(labelsets_dsl::owner.eq(owner));
+        }
+        sets
+    };
+
+    let total = filtered().count().get_result::<i64>(&*conn)?;
+    let sets = match query.sort() {
+        SortOrder::Name => filtered().order(labelsets_dsl::name.asc()),
+        SortOrder::NameDesc => filtered().order(labelsets_dsl::name.desc()),
+        SortOrder::Newest => filtered().order(labelsets_dsl::id.desc()),
+        SortOrder::Oldest => filtered().order(labelsets_dsl::id.asc()),
+    }
+    .limit(query.per_page())
+    .offset(query.offset())
+    .load::<crate::models::LabelSet>(&*conn)?;
+
+    let items = sets.into_iter().map(From::from).collect();
+    Ok(Json(query.to_page(items, total)))
 }
 
 #[get("/uuid/<uuid>")]
 pub fn load_by_uuid(
-    _auth: &authentication::User,
+    auth: &authentication::User,
     conn: MainDbConn,
     uuid: Uuid,
 ) -> Result<Option<Json<JsonLabelSet>>, Box<dyn Error>> {
@@ -163,8 +538,8 @@ pub fn load_by_uuid(
         .pop();
 
     let labelset = match labelset {
-        Some(l) => l,
-        None => return Ok(None),
+        Some(l) if publication::is_visible(&auth.0, l.status) => l,
+        _ => return Ok(None),
     };
 
     let labels: Vec<crate::models::Label> = labels_dsl::labels
@@ -177,7 +552,7 @@ pub fn load_by_uuid(
 
 #[get("/<id>")]
 pub fn load(
-    _auth: &authentication::User,
+    auth: &authentication::User,
     conn: MainDbConn,
     id: i32,
 ) -> Result<Option<Json<JsonLabelSet>>, Box<dyn Error>> {
@@ -190,8 +565,8 @@ pub fn load(
         .pop();
 
     let labelset = match labelset {
-        Some(l) => l,
-        None => return Ok(None),
+        Some(l) if publication::is_visible(&auth.0, l.status) => l,
+        _ => return Ok(None),
     };
 
     let labels: Vec<crate::models::Label> = labels_dsl::labels
@@ -210,6 +585,7 @@ pub fn delete(
 ) -> Result<Option<()>, Box<dyn Error>> {
     use crate::schema::labels::dsl as labels_dsl;
     use crate::schema::labelsets::dsl as labelsets_dsl;
+    use crate::schema::reviews::dsl as reviews_dsl;
     use crate::schema::userlabelsets::dsl as user_labelsets_dsl;
 
     let uuid = uuid.to_string();
@@ -232,6 +608,124 @@ pub fn delete(
     rocket_contrib::databases::diesel::delete(user_labelsets_dsl::userlabelsets)
         .filter(user_labelsets_dsl::labelset.eq(&labelset.id))
         .execute(&*conn)?;
+    rocket_contrib::databases::diesel::delete(reviews_dsl::reviews)
+        .filter(reviews_dsl::labelset.eq(&labelset.id))
+        .execute(&*conn)?;
+    sharing::delete_for_label_set(&*conn, labelset.id)?;
+    media::retain_for_labels(&*conn, labelset.id, &[])?;
+    search::remove(&*conn, search::KIND_LABELSET, &uuid)?;
+    search::remove(&*conn, search::KIND_LABEL, &uuid)?;
 
     Ok(Some(()))
 }
+
+/// Publishes, archives or returns a label set to draft. Only the owner of the set may.
+#[put("/<uuid>/status", format = "json", data = "<status>")]
+pub fn set_status(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+    status: Json<Publication>,
+) -> Result<Option<Status>, Box<dyn Error>> {
+    use crate::schema::labelsets::dsl as labelsets_dsl;
+
+    let set = labelsets_dsl::labelsets
+        .filter(labelsets_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<models::LabelSet>(&*conn)?
+        .pop();
+    let set = match set {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+    if !authentication::may_edit(&auth.0, set.owner) {
+        return Ok(Some(Status::Forbidden));
+    }
+
+    rocket_contrib::databases::diesel::update(labelsets_dsl::labelsets.find(&set.id))
+        .set(labelsets_dsl::status.eq(status.to_db()))
+        .execute(&*conn)?;
+    Ok(Some(Status::Ok))
+}
+
+/// Suggests a palette of `n` distinct, colour blind friendly colours for labels.
+#[get("/palette/<n>")]
+pub fn palette(_auth: &authentication::User, n: usize) -> Json<Vec<Colour>> {
+    const MAX_PALETTE_SIZE: usize = 256;
+    Json(colour::palette(n.min(MAX_PALETTE_SIZE)))
+}
+
+/// Replaces the colours of every label in a set with a generated palette.
+#[post("/<uuid>/recolour")]
+pub fn recolour(
+    _auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+) -> Result<Option<Json<JsonLabelSet>>, Box<dyn Error>> {
+    use crate::schema::labels::dsl as labels_dsl;
+    use crate::schema::labelsets::dsl as labelsets_dsl;
+
+    let labelset = labelsets_dsl::labelsets
+        .filter(labelsets_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<crate::models::LabelSet>(&*conn)?
+        .pop();
+    let labelset = match labelset {
+        Some(l) => l,
+        None => return Ok(None),
+    };
+
+    let mut labels: Vec<crate::models::Label> = labels_dsl::labels
+        .filter(labels_dsl::labelset.eq(&labelset.id))
+        .order(labels_dsl::id)
+        .load::<crate::models::Label>(&*conn)?;
+
+    let palette = colour::palette(labels.len());
+    for (label, colour) in labels.iter_mut().zip(palette) {
+        label.colour = colour.to_string();
+        rocket_contrib::databases::diesel::update(labels_dsl::labels.find(label.id))
+            .set(labels_dsl::colour.eq(&label.colour))
+            .execute(&*conn)?;
+    }
+
+    let result = JsonLabelSet::from_db(labelset, labels);
+    Ok(Some(Json(result)))
+}
+
+/// Reports overlapping labels, empty labels and how much of the model is left unlabelled.
+#[get("/analysis/<uuid>")]
+pub fn analyse(
+    _auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+) -> Result<Option<Json<JsonLabelSetAnalysis>>, Box<dyn Error>> {
+    use crate::schema::labels::dsl as labels_dsl;
+    use crate::schema::labelsets::dsl as labelsets_dsl;
+
+    let labelset = labelsets_dsl::labelsets
+        .filter(labelsets_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<crate::models::LabelSet>(&*conn)?
+        .pop();
+    let labelset = match labelset {
+        Some(l) => l,
+        None => return Ok(None),
+    };
+
+    let labels: Vec<JsonLabel> = labels_dsl::labels
+        .filter(labels_dsl::labelset.eq(&labelset.id))
+        .load::<crate::models::Label>(&*conn)?
+        .into_iter()
+        .map(From::from)
+        .collect();
+
+    let mesh_vertices = crate::schema::models::dsl::models
+        .find(labelset.model)
+        .load::<crate::models::Model>(&*conn)?
+        .pop()
+        .and_then(|model| Mesh::load(&model.filename).ok())
+        .map(|mesh| mesh.positions.len());
+
+    let result = analysis::analyse(&label_vertices(&labels), mesh_vertices);
+    Ok(Some(Json(result)))
+}
@@ -0,0 +1,103 @@
+use crate::{publication::Publication, schema::models::dsl as models_dsl};
+use diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl, SqliteConnection};
+use rocket::{FromForm, FromFormValue};
+use serde::Serialize;
+
+const DEFAULT_PAGE_SIZE: i64 = 25;
+const MAX_PAGE_SIZE: i64 = 100;
+
+/// Query parameters shared by the labelset and quiz catalogues.
+#[derive(FromForm, Debug)]
+pub struct ListQuery {
+    /// Zero-based page number.
+    pub page: Option<i64>,
+    #[form(field = "perPage")]
+    pub per_page: Option<i64>,
+    pub sort: Option<SortOrder>,
+    pub model: Option<i32>,
+    pub category: Option<String>,
+    pub name: Option<String>,
+    pub owner: Option<i32>,
+    /// Only honoured for moderators, as regular users only see published items.
+    pub status: Option<Publication>,
+}
+
+#[derive(FromFormValue, Debug, Clone, Copy)]
+pub enum SortOrder {
+    #[form(value = "name")]
+    Name,
+    #[form(value = "-name")]
+    NameDesc,
+    #[form(value = "newest")]
+    Newest,
+    #[form(value = "oldest")]
+    Oldest,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonPage<T> {
+    pub items: Vec<T>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
+
+impl ListQuery {
+    pub fn page(&self) -> i64 {
+        self.page.unwrap_or(0).max(0)
+    }
+
+    pub fn per_page(&self) -> i64 {
+        self.per_page
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    /// Pages too far out to exist give an offset past every item rather than overflowing.
+    pub fn offset(&self) -> i64 {
+        self.page().saturating_mul(self.per_page())
+    }
+
+    pub fn sort(&self) -> SortOrder {
+        self.sort.unwrap_or(SortOrder::Name)
+    }
+
+    /// A `LIKE` pattern matching names containing the requested substring, escaped with `\`.
+    pub fn name_pattern(&self) -> Option<String> {
+        self.name.as_ref().map(|name| {
+            let escaped = name
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("%{}%", escaped)
+        })
+    }
+
+    /// Resolves the model and category filters to the IDs of matching models, or `None` if
+    /// neither filter is in use.
+    pub fn model_ids(&self, conn: &SqliteConnection) -> QueryResult<Option<Vec<i32>>> {
+        if self.model.is_none() && self.category.is_none() {
+            return Ok(None);
+        }
+
+        let mut query = models_dsl::models.select(models_dsl::id).into_boxed();
+        if let Some(model) = self.model {
+            query = query.filter(models_dsl::id.eq(model));
+        }
+        if let Some(category) = &self.category {
+            query = query.filter(models_dsl::category.eq(category));
+        }
+
+        query.load::<i32>(conn).map(Some)
+    }
+
+    pub fn to_page<T>(&self, items: Vec<T>, total: i64) -> JsonPage<T> {
+        JsonPage {
+            items,
+            page: self.page(),
+            per_page: self.per_page(),
+            total,
+        }
+    }
+}
@@ -0,0 +1,591 @@
+//! Live runs of a quiz, where a moderator moves everyone through the questions together.
+//! Participants are sent each question as it's asked and answer it while it's open, and the
+//! moderator is sent the results as the answers come in. Questions and results are streamed as
+//! server-sent events. Sessions are kept in memory only, and end when the server restarts.
+
+use crate::{
+    attempts::{self, Grader, JsonAnswer},
+    authentication, grading, models,
+    quiz::{self, DeliveredKind, JsonQuestion, QuestionKind},
+    schema::quizzes::dsl as quizzes_dsl,
+    util, MainDbConn,
+};
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use rocket::{
+    get,
+    http::{ContentType, Status},
+    post, put,
+    response::{status, Content, Stream},
+    State,
+};
+use rocket_contrib::{json::Json, uuid::Uuid};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    io::{self, Read},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Mutex, MutexGuard, PoisonError,
+    },
+    time::Duration,
+};
+
+/// Letters that join codes are made of, leaving out those easily mistaken for each other.
+const CODE_LETTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 6;
+
+/// Sessions left this many seconds without being advanced are ended when another one starts.
+const MAX_IDLE_SECONDS: i64 = 6 * 60 * 60;
+
+/// How often a comment is sent on an idle event stream, so that proxies don't close it.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+const KEEP_ALIVE_EVENT: &str = ": keep-alive\n\n";
+
+/// How many result views a session may have open. Opening another closes the oldest.
+const MAX_PROJECTORS: usize = 2;
+
+/// How many participants may be connected across all sessions when `LIVE_PARTICIPANT_LIMIT`
+/// isn't set. Each takes up one of Rocket's workers for as long as they're connected.
+pub const DEFAULT_PARTICIPANT_LIMIT: usize = 100;
+
+/// The live sessions that are running, by join code, and how many participants may be connected
+/// to them at once.
+pub struct Sessions {
+    sessions: Mutex<HashMap<String, Session>>,
+    participant_limit: usize,
+}
+
+struct Session {
+    quiz: String,
+    owner: i32,
+    questions: Vec<JsonQuestion>,
+    grader: Grader,
+    /// The index of the question being asked, if any has been yet.
+    current: Option<usize>,
+    /// When the session was started or last advanced.
+    active_at: i64,
+    /// The names of those who have joined, by user ID.
+    names: HashMap<i32, String>,
+    /// The answers to each question asked so far, by user ID.
+    answers: Vec<HashMap<i32, JsonAnswer>>,
+    /// The event streams of the participants, by user ID. A participant that connects again
+    /// replaces their earlier stream, which is then closed.
+    participants: HashMap<i32, Sender<String>>,
+    /// The event streams of the result views.
+    projectors: Vec<Sender<String>>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonSession {
+    /// The code participants join with.
+    pub code: String,
+    pub quiz: String,
+    pub question: Option<JsonLiveQuestion>,
+    pub participants: usize,
+}
+
+/// A question as sent to participants, without its answer.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonLiveQuestion {
+    pub id: Option<i32>,
+    /// The position of the question in the session, from 0.
+    pub index: usize,
+    pub count: usize,
+    #[serde(flatten)]
+    pub kind: DeliveredKind,
+    pub text_prompt: String,
+    pub show_regions: Option<bool>,
+}
+
+/// The answers given to a question so far.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonLiveResults {
+    pub index: usize,
+    pub participants: usize,
+    pub responses: usize,
+    pub correct: usize,
+    pub average_score: Option<f64>,
+    /// How many picked each option of a multiple choice question.
+    pub choices: Vec<usize>,
+    /// How many picked each label, for questions answered by picking one.
+    pub labels: BTreeMap<i32, usize>,
+    /// The written answers given, normalised, most common first.
+    pub texts: Vec<JsonTally>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonTally {
+    pub answer: String,
+    pub count: usize,
+}
+
+/// The results of a session once it has ended.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonLiveSummary {
+    pub results: Vec<JsonLiveResults>,
+    /// The total score of each participant, highest first.
+    pub scores: Vec<JsonLiveScore>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonLiveScore {
+    pub username: String,
+    pub score: f64,
+}
+
+/// Either the session, or `Forbidden` for moderators that don't run it.
+pub type SessionResult = Result<Json<JsonSession>, Status>;
+
+/// Either the graded answer, or why it wasn't accepted.
+pub type AnswerResult = Result<Json<JsonAnswer>, status::Forbidden<Json<String>>>;
+
+/// Either the event stream, or `Forbidden` for moderators that don't run the session, or
+/// `ServiceUnavailable` when too many participants are connected.
+pub type EventsResult = Result<Content<Stream<EventStream>>, Status>;
+
+impl Sessions {
+    pub fn new(participant_limit: usize) -> Self {
+        Sessions {
+            sessions: Mutex::new(HashMap::new()),
+            participant_limit,
+        }
+    }
+
+    /// Reads the participant limit from `LIVE_PARTICIPANT_LIMIT`.
+    pub fn from_env() -> Result<Self, std::num::ParseIntError> {
+        let limit = match std::env::var("LIVE_PARTICIPANT_LIMIT") {
+            Ok(limit) => limit.parse()?,
+            Err(_) => DEFAULT_PARTICIPANT_LIMIT,
+        };
+        Ok(Self::new(limit))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Session>> {
+        // A panic while holding the lock can't leave a session half changed in a way that matters.
+        self.sessions.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Whether another participant may connect without going over the limit. Streams that have been
+/// closed are only noticed when something is sent on them, so they're pinged before giving up.
+fn has_room(sessions: &mut HashMap<String, Session>, limit: usize) -> bool {
+    let connected = |sessions: &HashMap<String, Session>| -> usize {
+        sessions.values().map(|s| s.participants.len()).sum()
+    };
+    if connected(sessions) < limit {
+        return true;
+    }
+    for session in sessions.values_mut() {
+        session
+            .participants
+            .retain(|_, stream| stream.send(KEEP_ALIVE_EVENT.to_owned()).is_ok());
+    }
+    connected(sessions) < limit
+}
+
+impl Session {
+    fn may_run(&self, user: &models::User) -> bool {
+        authentication::may_edit(user, Some(self.owner))
+    }
+
+    fn to_json(&self, code: &str) -> JsonSession {
+        JsonSession {
+            code: code.to_owned(),
+            quiz: self.quiz.clone(),
+            question: self.current.map(|i| self.live_question(i)),
+            participants: self.names.len(),
+        }
+    }
+
+    fn live_question(&self, index: usize) -> JsonLiveQuestion {
+        let question = &self.questions[index];
+        let mut kind = question.kind.to_delivered();
+        if let DeliveredKind::MultipleChoice { options } = &mut kind {
+            for option in options.iter_mut().filter(|o| o.text.is_none()) {
+                option.text = option
+                    .label_id
+                    .and_then(|l| self.grader.label_name(l))
+                    .map(str::to_owned);
+            }
+        }
+        JsonLiveQuestion {
+            id: question.id,
+            index,
+            count: self.questions.len(),
+            kind,
+            text_prompt: question.text_prompt.clone(),
+            show_regions: question.show_regions,
+        }
+    }
+
+    fn results(&self, index: usize) -> JsonLiveResults {
+        let answers = &self.answers[index];
+        let mut choices = match &self.questions[index].kind {
+            QuestionKind::MultipleChoice { options, .. } => vec![0; options.len()],
+            _ => Vec::new(),
+        };
+        let mut labels = BTreeMap::new();
+        let mut texts: HashMap<String, usize> = HashMap::new();
+        for answer in answers.values() {
+            for choice in &answer.choices {
+                if let Some(count) = choices.get_mut(*choice as usize) {
+                    *count += 1;
+                }
+            }
+            if let Some(label) = answer.label_id {
+                *labels.entry(label).or_insert(0) += 1;
+            }
+            if let Some(text) = answer.text.as_deref().map(grading::normalise) {
+                *texts.entry(text).or_insert(0) += 1;
+            }
+        }
+        let mut texts: Vec<JsonTally> = texts
+            .into_iter()
+            .map(|(answer, count)| JsonTally { answer, count })
+            .collect();
+        texts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.answer.cmp(&b.answer)));
+
+        let scores: Vec<f64> = answers.values().filter_map(|a| a.score).collect();
+        JsonLiveResults {
+            index,
+            participants: self.names.len(),
+            responses: answers.len(),
+            correct: scores.iter().filter(|s| **s >= 1.0).count(),
+            average_score: if scores.is_empty() {
+                None
+            } else {
+                Some(scores.iter().sum::<f64>() / scores.len() as f64)
+            },
+            choices,
+            labels,
+            texts,
+        }
+    }
+
+    fn summary(&self) -> JsonLiveSummary {
+        let mut totals: HashMap<i32, f64> = self.names.keys().map(|id| (*id, 0.0)).collect();
+        for answer in self.answers.iter().flat_map(|a| a.iter()) {
+            *totals.entry(*answer.0).or_insert(0.0) += answer.1.score.unwrap_or_default();
+        }
+        let mut scores: Vec<JsonLiveScore> = totals
+            .into_iter()
+            .map(|(id, score)| JsonLiveScore {
+                username: self.names.get(&id).cloned().unwrap_or_default(),
+                score,
+            })
+            .collect();
+        scores.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.username.cmp(&b.username))
+        });
+        JsonLiveSummary {
+            results: (0..self.answers.len()).map(|i| self.results(i)).collect(),
+            scores,
+        }
+    }
+
+    /// Sends the results of the current question to the result views.
+    fn push_results(&mut self) {
+        if let Some(index) = self.current {
+            let event = event("results", &self.results(index));
+            broadcast(&mut self.projectors, &event);
+        }
+    }
+
+    /// Sends an event to every participant, dropping those that have disconnected.
+    fn broadcast_participants(&mut self, event: &str) {
+        self.participants
+            .retain(|_, stream| stream.send(event.to_owned()).is_ok());
+    }
+}
+
+/// Formats a server-sent event with a JSON payload.
+fn event<T: Serialize>(name: &str, data: &T) -> String {
+    let data = serde_json::to_string(data).unwrap_or_default();
+    format!("event: {}\ndata: {}\n\n", name, data)
+}
+
+/// Sends an event to every stream, dropping those that have been closed.
+fn broadcast(streams: &mut Vec<Sender<String>>, event: &str) {
+    streams.retain(|stream| stream.send(event.to_owned()).is_ok());
+}
+
+fn create_code(sessions: &HashMap<String, Session>) -> String {
+    loop {
+        let code: String = (0..CODE_LENGTH)
+            .map(|_| {
+                let i = sodiumoxide::randombytes::randombytes_uniform(CODE_LETTERS.len() as u32);
+                CODE_LETTERS[i as usize] as char
+            })
+            .collect();
+        if !sessions.contains_key(&code) {
+            return code;
+        }
+    }
+}
+
+/// The events sent to a stream, as read by the response. Rocket flushes the response whenever
+/// the reader would block, which is done after every event so that it goes out right away.
+pub struct EventStream {
+    events: Receiver<String>,
+    pending: Vec<u8>,
+    position: usize,
+    flush: bool,
+}
+
+impl EventStream {
+    fn respond(events: Receiver<String>) -> Content<Stream<EventStream>> {
+        let stream = EventStream {
+            events,
+            pending: Vec::new(),
+            position: 0,
+            flush: false,
+        };
+        Content(
+            ContentType::new("text", "event-stream"),
+            Stream::chunked(stream, 4096),
+        )
+    }
+}
+
+impl Read for EventStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.pending.len() {
+            if self.flush {
+                self.flush = false;
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            self.pending = match self.events.recv_timeout(KEEP_ALIVE) {
+                Ok(event) => event.into_bytes(),
+                Err(RecvTimeoutError::Timeout) => KEEP_ALIVE_EVENT.as_bytes().to_vec(),
+                // The session has ended, or the participant connected again elsewhere.
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+            self.position = 0;
+        }
+
+        let count = buf.len().min(self.pending.len() - self.position);
+        buf[..count].copy_from_slice(&self.pending[self.position..self.position + count]);
+        self.position += count;
+        self.flush = self.position == self.pending.len();
+        Ok(count)
+    }
+}
+
+/// Starts a live session of a quiz, drawing its questions as for an attempt. No question is
+/// asked until the session is advanced.
+#[post("/<uuid>")]
+pub fn start(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    sessions: State<Sessions>,
+    uuid: Uuid,
+) -> Result<Option<SessionResult>, Box<dyn Error>> {
+    let quiz = quizzes_dsl::quizzes
+        .filter(quizzes_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<models::Quiz>(&*conn)?
+        .pop();
+    let quiz = match quiz {
+        Some(q) => q,
+        None => return Ok(None),
+    };
+    if !authentication::may_edit(&auth.0, quiz.owner) {
+        return Ok(Some(Err(Status::Forbidden)));
+    }
+
+    let label_set = quiz.labelset;
+    // The options are shuffled along with the questions, and answers graded in that order.
+    let questions = attempts::draw_questions(quiz::from_db(&*conn, quiz)?, util::random_seed());
+    let grader = Grader::load(&*conn, label_set, &questions)?;
+    let session = Session {
+        quiz: uuid.to_string(),
+        owner: auth.0.id,
+        questions,
+        grader,
+        current: None,
+        active_at: util::now(),
+        names: HashMap::new(),
+        answers: Vec::new(),
+        participants: HashMap::new(),
+        projectors: Vec::new(),
+    };
+
+    let mut sessions = sessions.lock();
+    let now = util::now();
+    sessions.retain(|_, s| now - s.active_at < MAX_IDLE_SECONDS);
+    let code = create_code(&sessions);
+    let json = session.to_json(&code);
+    sessions.insert(code, session);
+    Ok(Some(Ok(Json(json))))
+}
+
+/// Loads a session and the question being asked.
+#[get("/<code>")]
+pub fn load(
+    _user: &authentication::User,
+    sessions: State<Sessions>,
+    code: String,
+) -> Option<Json<JsonSession>> {
+    let sessions = sessions.lock();
+    sessions.get(&code).map(|s| Json(s.to_json(&code)))
+}
+
+/// Asks the next question, closing the one before it. Participants are sent the question, and
+/// result views its empty results.
+#[post("/<code>/advance")]
+pub fn advance(
+    auth: authentication::Moderator,
+    sessions: State<Sessions>,
+    code: String,
+) -> Option<SessionResult> {
+    let mut sessions = sessions.lock();
+    let session = sessions.get_mut(&code)?;
+    if !session.may_run(&auth.0) {
+        return Some(Err(Status::Forbidden));
+    }
+    let next = session.current.map_or(0, |i| i + 1);
+    if next >= session.questions.len() {
+        return Some(Err(Status::Conflict));
+    }
+
+    session.current = Some(next);
+    session.active_at = util::now();
+    session.answers.push(HashMap::new());
+    let question = event("question", &session.live_question(next));
+    session.broadcast_participants(&question);
+    session.push_results();
+    Some(Ok(Json(session.to_json(&code))))
+}
+
+/// Ends a session. Everyone connected is sent the summary before their streams are closed.
+#[post("/<code>/close")]
+pub fn close(
+    auth: authentication::Moderator,
+    sessions: State<Sessions>,
+    code: String,
+) -> Option<Result<Json<JsonLiveSummary>, Status>> {
+    let mut sessions = sessions.lock();
+    if !sessions.get(&code)?.may_run(&auth.0) {
+        return Some(Err(Status::Forbidden));
+    }
+
+    let mut session = sessions.remove(&code)?;
+    let summary = session.summary();
+    let end = event("end", &summary);
+    session.broadcast_participants(&end);
+    broadcast(&mut session.projectors, &end);
+    Some(Ok(Json(summary)))
+}
+
+/// Joins a session, streaming each question as it's asked, starting with the current one.
+/// Joining again closes the earlier stream, and joins are turned away once the participant limit
+/// is reached.
+#[get("/<code>/events")]
+pub fn events(
+    user: &authentication::User,
+    sessions: State<Sessions>,
+    code: String,
+) -> Option<EventsResult> {
+    let limit = sessions.participant_limit;
+    let mut sessions = sessions.lock();
+    let reconnecting = sessions.get(&code)?.participants.contains_key(&user.0.id);
+    if !reconnecting && !has_room(&mut sessions, limit) {
+        return Some(Err(Status::ServiceUnavailable));
+    }
+
+    let session = sessions.get_mut(&code)?;
+    let (sender, receiver) = mpsc::channel();
+    if let Some(index) = session.current {
+        sender
+            .send(event("question", &session.live_question(index)))
+            .ok()?;
+    }
+    session.participants.insert(user.0.id, sender);
+    if session
+        .names
+        .insert(user.0.id, user.0.username.clone())
+        .is_none()
+    {
+        session.push_results();
+    }
+    Some(Ok(EventStream::respond(receiver)))
+}
+
+/// Answers the question being asked. Each participant may answer a question once.
+#[put("/<code>/answer", format = "json", data = "<answer>")]
+pub fn answer(
+    user: &authentication::User,
+    sessions: State<Sessions>,
+    code: String,
+    answer: Json<JsonAnswer>,
+) -> Option<AnswerResult> {
+    let mut sessions = sessions.lock();
+    let session = sessions.get_mut(&code)?;
+    let index = match session.current {
+        Some(i) if session.questions[i].id == Some(answer.question) => i,
+        _ => return Some(forbidden("That question is not being asked.")),
+    };
+    if session.answers[index].contains_key(&user.0.id) {
+        return Some(forbidden("The question has already been answered."));
+    }
+
+    let mut answer = answer.into_inner();
+    let (score, verdict) = session.grader.grade(&session.questions[index], &answer);
+    answer.score = Some(score);
+    answer.verdict = verdict;
+    // Only the grading is sent back, as the participant knows what they answered.
+    let graded = JsonAnswer {
+        question: answer.question,
+        score: answer.score,
+        verdict: answer.verdict,
+        ..Default::default()
+    };
+    session
+        .names
+        .entry(user.0.id)
+        .or_insert_with(|| user.0.username.clone());
+    session.answers[index].insert(user.0.id, answer);
+    session.push_results();
+    Some(Ok(Json(graded)))
+}
+
+/// Streams the results of each question as the answers come in, starting with the current one.
+#[get("/<code>/results")]
+pub fn results(
+    auth: authentication::Moderator,
+    sessions: State<Sessions>,
+    code: String,
+) -> Option<EventsResult> {
+    let mut sessions = sessions.lock();
+    let session = sessions.get_mut(&code)?;
+    if !session.may_run(&auth.0) {
+        return Some(Err(Status::Forbidden));
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    if let Some(index) = session.current {
+        sender
+            .send(event("results", &session.results(index)))
+            .ok()?;
+    }
+    if session.projectors.len() >= MAX_PROJECTORS {
+        // Dropping the stream closes it, freeing its worker.
+        session.projectors.remove(0);
+    }
+    session.projectors.push(sender);
+    Some(Ok(EventStream::respond(receiver)))
+}
+
+fn forbidden(reason: &str) -> AnswerResult {
+    Err(status::Forbidden(Some(Json(reason.to_owned()))))
+}
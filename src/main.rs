@@ -6,12 +6,26 @@ extern crate diesel;
 
 use rocket::routes;
 use rocket_contrib::{database, serve::StaticFiles};
+mod analysis;
+mod attempts;
 mod authentication;
+mod colour;
+mod exchange;
+mod geometry;
+mod gradebook;
+mod grading;
 mod labels;
+mod listing;
+mod live;
+mod media;
 mod models;
 mod modelstorage;
+mod practice;
+mod publication;
 mod quiz;
 mod schema;
+mod search;
+mod sharing;
 mod users;
 mod util;
 
@@ -36,6 +50,21 @@ fn main() {
         return;
     }
 
+    // Media are optional, and kept in `./media` unless configured otherwise.
+    if let Err(e) = std::fs::create_dir_all(media::media_dir()) {
+        eprintln!("Path for 'MEDIA_DIR' could not be created: {:?}", e);
+        return;
+    }
+
+    // Live sessions are limited in how many participants may be connected at once.
+    let sessions = match live::Sessions::from_env() {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            eprintln!("'LIVE_PARTICIPANT_LIMIT' is not a number: {:?}", e);
+            return;
+        }
+    };
+
     // Set up CORS as this API will be called from other pages.
     let mut allowed_origins = vec![
         r"^https?://localhost:(\d+){1,6}$".to_owned(),
@@ -54,18 +83,37 @@ fn main() {
     let mut rocket = rocket::ignite()
         .attach(MainDbConn::fairing())
         .attach(cors)
+        .manage(sessions)
         .mount(
             "/quiz",
-            routes![quiz::load, quiz::create, quiz::delete, quiz::put],
+            routes![
+                quiz::list,
+                quiz::load,
+                quiz::load_authoring,
+                quiz::create,
+                quiz::delete,
+                quiz::put,
+                quiz::reorder,
+                quiz::set_status,
+                quiz::generate,
+                quiz::analyse,
+                exchange::export,
+                exchange::import,
+            ],
         )
         .mount(
             "/labels",
             routes![
+                labels::list,
                 labels::create,
                 labels::load,
                 labels::put,
                 labels::delete,
                 labels::load_by_uuid,
+                labels::palette,
+                labels::recolour,
+                labels::analyse,
+                labels::set_status,
             ],
         )
         .mount(
@@ -73,6 +121,62 @@ fn main() {
             StaticFiles::from(std::env::var("MODELS_DIR").unwrap()).rank(isize::max_value() - 1),
         )
         .mount("/models", routes![models_index])
+        .mount("/search", routes![search::search])
+        .mount("/gradebook", routes![gradebook::quiz, gradebook::group])
+        .mount("/practice", routes![practice::next, practice::answer])
+        .mount(
+            "/attempts",
+            routes![
+                attempts::start,
+                attempts::load,
+                attempts::submit,
+                attempts::hint,
+            ],
+        )
+        .mount(
+            "/live",
+            routes![
+                live::start,
+                live::load,
+                live::advance,
+                live::close,
+                live::events,
+                live::answer,
+                live::results,
+            ],
+        )
+        .mount(
+            "/share",
+            routes![
+                sharing::share_quiz,
+                sharing::share_label_set,
+                sharing::quiz_links,
+                sharing::label_set_links,
+                sharing::update,
+                sharing::revoke,
+            ],
+        )
+        .mount(
+            "/shared",
+            routes![
+                sharing::open,
+                sharing::start_attempt,
+                sharing::load_attempt,
+                sharing::submit_attempt,
+                sharing::reveal_hint,
+            ],
+        )
+        .mount(
+            "/media",
+            routes![
+                media::upload,
+                media::load,
+                media::delete,
+                media::attach,
+                media::detach,
+                media::label_set,
+            ],
+        )
         .mount(
             "/modelstorage",
             routes![
@@ -0,0 +1,404 @@
+use crate::{
+    authentication, models,
+    modelstorage::write_limited,
+    publication,
+    schema::{
+        attachments::dsl as attachments_dsl, labels::dsl as labels_dsl,
+        labelsets::dsl as labelsets_dsl, media::dsl as media_dsl, questions::dsl as questions_dsl,
+        quizzes::dsl as quizzes_dsl,
+    },
+    util, MainDbConn,
+};
+use diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl, SqliteConnection};
+use rocket::{
+    delete, get,
+    http::{ContentType, Status},
+    post, put,
+    response::Content,
+    Data,
+};
+use rocket_contrib::{json::Json, uuid::Uuid};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, error::Error, fs::File, path::PathBuf};
+
+const MIB: u64 = 1024u64.pow(2);
+const MEDIA_SIZE_LIMIT: u64 = 20 * MIB;
+
+/// The types of media that can be uploaded, by file extension.
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+    ("oga", "audio/ogg"),
+    ("wav", "audio/wav"),
+    ("m4a", "audio/mp4"),
+    ("flac", "audio/flac"),
+];
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonMedia {
+    pub uuid: String,
+    /// The name of the file as uploaded.
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    pub uploaded_at: i64,
+}
+
+impl From<models::Media> for JsonMedia {
+    fn from(media: models::Media) -> Self {
+        Self {
+            uuid: media.uuid,
+            filename: media.filename,
+            content_type: media.contenttype,
+            size: media.size,
+            uploaded_at: media.uploadedat,
+        }
+    }
+}
+
+/// What to attach media to: either a question, or a label by its set and name.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonAttachTo {
+    #[serde(default)]
+    pub question: Option<i32>,
+    #[serde(default)]
+    pub label_set: Option<uuid::Uuid>,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonAttachment {
+    pub id: i32,
+    pub media: JsonMedia,
+    pub question: Option<i32>,
+    pub label: Option<String>,
+}
+
+/// Either the uploaded media, or `PayloadTooLarge` or `UnsupportedMediaType`.
+pub type UploadResult = Result<Json<JsonMedia>, Status>;
+
+/// Either the attachment, or what was wrong with the request.
+pub type AttachResult = Result<Json<JsonAttachment>, Status>;
+
+/// The directory media are kept in, set by `MEDIA_DIR`.
+pub fn media_dir() -> PathBuf {
+    std::env::var("MEDIA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("media"))
+}
+
+/// Files are stored by the UUID of their media, so uploaded names never reach the file system.
+fn media_path(uuid: &str) -> PathBuf {
+    let mut path = media_dir();
+    path.push(uuid);
+    path
+}
+
+fn content_type(filename: &str) -> Option<&'static str> {
+    let extension = filename.rsplit('.').next()?.to_lowercase();
+    CONTENT_TYPES
+        .iter()
+        .find(|(e, _)| *e == extension)
+        .map(|(_, content_type)| *content_type)
+}
+
+fn load_media(conn: &SqliteConnection, uuid: &Uuid) -> QueryResult<Option<models::Media>> {
+    Ok(media_dsl::media
+        .filter(media_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<models::Media>(conn)?
+        .pop())
+}
+
+/// Uploads an image or audio clip, with its type given by the extension of the file name.
+#[put("/<filename>", data = "<data>")]
+pub fn upload(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    filename: String,
+    data: Data,
+) -> Result<UploadResult, Box<dyn Error>> {
+    let content_type = match content_type(&filename) {
+        Some(t) => t,
+        None => return Ok(Err(Status::UnsupportedMediaType)),
+    };
+
+    let uuid = util::create_uuid().to_string();
+    let size = match write_limited(data.open(), &media_path(&uuid), MEDIA_SIZE_LIMIT)? {
+        Some(size) => size,
+        None => return Ok(Err(Status::PayloadTooLarge)),
+    };
+    rocket_contrib::databases::diesel::insert_into(media_dsl::media)
+        .values(&models::NewMedia {
+            uuid: &uuid,
+            filename: &filename,
+            contenttype: content_type,
+            size: size as i64,
+            owner: Some(auth.0.id),
+            uploadedat: util::now(),
+        })
+        .execute(&*conn)?;
+
+    let media = media_dsl::media
+        .filter(media_dsl::uuid.eq(&uuid))
+        .limit(1)
+        .load::<models::Media>(&*conn)?
+        .pop()
+        .ok_or("Can't find media that was just inserted.")?;
+    Ok(Ok(Json(media.into())))
+}
+
+/// Serves the file of a media. Needs no account, as media are shown along with shared quizzes.
+#[get("/<uuid>")]
+pub fn load(conn: MainDbConn, uuid: Uuid) -> Result<Option<Content<File>>, Box<dyn Error>> {
+    let media = match load_media(&*conn, &uuid)? {
+        Some(m) => m,
+        None => return Ok(None),
+    };
+
+    let file = File::open(media_path(&media.uuid))?;
+    let content_type =
+        ContentType::parse_flexible(&media.contenttype).unwrap_or(ContentType::Binary);
+    Ok(Some(Content(content_type, file)))
+}
+
+/// Deletes a media along with its file and attachments.
+#[delete("/<uuid>")]
+pub fn delete(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+) -> Result<Option<Status>, Box<dyn Error>> {
+    let media = match load_media(&*conn, &uuid)? {
+        Some(m) => m,
+        None => return Ok(None),
+    };
+    if !authentication::may_edit(&auth.0, media.owner) {
+        return Ok(Some(Status::Forbidden));
+    }
+
+    rocket_contrib::databases::diesel::delete(attachments_dsl::attachments)
+        .filter(attachments_dsl::media.eq(&media.id))
+        .execute(&*conn)?;
+    rocket_contrib::databases::diesel::delete(media_dsl::media.find(&media.id)).execute(&*conn)?;
+    std::fs::remove_file(media_path(&media.uuid))?;
+
+    Ok(Some(Status::Ok))
+}
+
+/// Attaches a media to a question or a label. Only the owner of both the media and the quiz or
+/// label set may.
+#[post("/<uuid>/attachments", format = "json", data = "<target>")]
+pub fn attach(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+    target: Json<JsonAttachTo>,
+) -> Result<Option<AttachResult>, Box<dyn Error>> {
+    let media = match load_media(&*conn, &uuid)? {
+        Some(m) => m,
+        None => return Ok(None),
+    };
+    if !authentication::may_edit(&auth.0, media.owner) {
+        return Ok(Some(Err(Status::Forbidden)));
+    }
+
+    let (question, labelset, owner) = match (&target.question, &target.label_set, &target.label) {
+        (Some(question), None, None) => {
+            let quiz = questions_dsl::questions
+                .find(question)
+                .select(questions_dsl::quiz)
+                .load::<i32>(&*conn)?
+                .pop();
+            let owner = match quiz {
+                Some(quiz) => quizzes_dsl::quizzes
+                    .find(&quiz)
+                    .select(quizzes_dsl::owner)
+                    .load::<Option<i32>>(&*conn)?
+                    .pop()
+                    .flatten(),
+                None => return Ok(None),
+            };
+            (Some(*question), None, owner)
+        }
+        (None, Some(label_set), Some(label)) => {
+            let set = labelsets_dsl::labelsets
+                .filter(labelsets_dsl::uuid.eq(&label_set.to_string()))
+                .limit(1)
+                .load::<models::LabelSet>(&*conn)?
+                .pop();
+            let set = match set {
+                Some(s) => s,
+                None => return Ok(None),
+            };
+            let labels: i64 = labels_dsl::labels
+                .filter(labels_dsl::labelset.eq(&set.id))
+                .filter(labels_dsl::name.eq(label))
+                .count()
+                .get_result(&*conn)?;
+            if labels == 0 {
+                return Ok(None);
+            }
+            (None, Some(set.id), set.owner)
+        }
+        _ => return Ok(Some(Err(Status::BadRequest))),
+    };
+    if !authentication::may_edit(&auth.0, owner) {
+        return Ok(Some(Err(Status::Forbidden)));
+    }
+
+    rocket_contrib::databases::diesel::insert_into(attachments_dsl::attachments)
+        .values(&models::NewAttachment {
+            media: media.id,
+            question,
+            labelset,
+            label: target.label.as_deref(),
+        })
+        .execute(&*conn)?;
+
+    let attachment = attachments_dsl::attachments
+        .filter(attachments_dsl::media.eq(&media.id))
+        .order(attachments_dsl::id.desc())
+        .limit(1)
+        .load::<models::Attachment>(&*conn)?
+        .pop()
+        .ok_or("Can't find attachment that was just inserted.")?;
+    Ok(Some(Ok(Json(JsonAttachment {
+        id: attachment.id,
+        media: media.into(),
+        question: attachment.question,
+        label: attachment.label,
+    }))))
+}
+
+/// Removes an attachment, keeping the media.
+#[delete("/attachments/<id>")]
+pub fn detach(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    id: i32,
+) -> Result<Option<Status>, Box<dyn Error>> {
+    let attachment = attachments_dsl::attachments
+        .find(&id)
+        .load::<models::Attachment>(&*conn)?
+        .pop();
+    let attachment = match attachment {
+        Some(a) => a,
+        None => return Ok(None),
+    };
+    let owner = media_dsl::media
+        .find(&attachment.media)
+        .select(media_dsl::owner)
+        .load::<Option<i32>>(&*conn)?
+        .pop()
+        .flatten();
+    if !authentication::may_edit(&auth.0, owner) {
+        return Ok(Some(Status::Forbidden));
+    }
+
+    rocket_contrib::databases::diesel::delete(attachments_dsl::attachments.find(&id))
+        .execute(&*conn)?;
+    Ok(Some(Status::Ok))
+}
+
+/// Lists the media attached to the labels of a set.
+#[get("/labels/<uuid>")]
+pub fn label_set(
+    auth: &authentication::User,
+    conn: MainDbConn,
+    uuid: Uuid,
+) -> Result<Option<Json<Vec<JsonAttachment>>>, Box<dyn Error>> {
+    let set = labelsets_dsl::labelsets
+        .filter(labelsets_dsl::uuid.eq(&uuid.to_string()))
+        .select((labelsets_dsl::id, labelsets_dsl::status))
+        .limit(1)
+        .load::<(i32, i16)>(&*conn)?
+        .pop();
+    let set = match set {
+        Some((id, status)) if publication::is_visible(&auth.0, status) => id,
+        _ => return Ok(None),
+    };
+
+    let attachments = attachments_dsl::attachments
+        .filter(attachments_dsl::labelset.eq(&set))
+        .order(attachments_dsl::id)
+        .load::<models::Attachment>(&*conn)?;
+    Ok(Some(Json(with_media(&*conn, attachments)?)))
+}
+
+fn with_media(
+    conn: &SqliteConnection,
+    attachments: Vec<models::Attachment>,
+) -> QueryResult<Vec<JsonAttachment>> {
+    let media_ids: Vec<i32> = attachments.iter().map(|a| a.media).collect();
+    let media: HashMap<i32, JsonMedia> = media_dsl::media
+        .filter(media_dsl::id.eq_any(&media_ids))
+        .load::<models::Media>(conn)?
+        .into_iter()
+        .map(|m| (m.id, JsonMedia::from(m)))
+        .collect();
+
+    Ok(attachments
+        .into_iter()
+        .filter_map(|a| {
+            Some(JsonAttachment {
+                id: a.id,
+                media: media.get(&a.media)?.clone(),
+                question: a.question,
+                label: a.label,
+            })
+        })
+        .collect())
+}
+
+/// The media attached to each of the questions, by question ID.
+pub fn for_questions(
+    conn: &SqliteConnection,
+    question_ids: &[i32],
+) -> QueryResult<HashMap<i32, Vec<JsonMedia>>> {
+    let attachments = attachments_dsl::attachments
+        .filter(attachments_dsl::question.eq_any(question_ids))
+        .order(attachments_dsl::id)
+        .load::<models::Attachment>(conn)?;
+
+    let mut by_question: HashMap<i32, Vec<JsonMedia>> = HashMap::new();
+    for attachment in with_media(conn, attachments)? {
+        if let Some(question) = attachment.question {
+            by_question
+                .entry(question)
+                .or_default()
+                .push(attachment.media);
+        }
+    }
+    Ok(by_question)
+}
+
+/// Removes the attachments to the given questions.
+pub fn delete_for_questions(conn: &SqliteConnection, question_ids: &[i32]) -> QueryResult<()> {
+    rocket_contrib::databases::diesel::delete(attachments_dsl::attachments)
+        .filter(attachments_dsl::question.eq_any(question_ids))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Removes the attachments to labels of a set that aren't among `names`.
+pub fn retain_for_labels(
+    conn: &SqliteConnection,
+    label_set_id: i32,
+    names: &[&str],
+) -> QueryResult<()> {
+    rocket_contrib::databases::diesel::delete(attachments_dsl::attachments)
+        .filter(attachments_dsl::labelset.eq(&label_set_id))
+        .filter(attachments_dsl::label.ne_all(names))
+        .execute(conn)?;
+    Ok(())
+}
@@ -40,10 +40,16 @@ pub struct NewUser<'a> {
 #[derive(Queryable, Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct Model {
     pub id: i32,
+    /// The name the file is stored under in `MODELS_DIR`.
     pub filename: String,
     pub material: Option<String>,
     pub texture: Option<String>,
     pub category: Option<String>,
+    /// The name the file was uploaded as.
+    pub name: String,
+    /// The SHA-256 of the file in hex, unknown for files uploaded before it was recorded.
+    pub checksum: Option<String>,
+    pub size: Option<i64>,
 }
 
 #[derive(Insertable, Default)]
@@ -53,6 +59,9 @@ pub struct NewModel<'a> {
     pub material: Option<&'a str>,
     pub texture: Option<&'a str>,
     pub category: Option<&'a str>,
+    pub name: &'a str,
+    pub checksum: Option<&'a str>,
+    pub size: Option<i64>,
 }
 
 #[derive(Queryable, Clone, Debug)]
@@ -61,6 +70,8 @@ pub struct LabelSet {
     pub uuid: String,
     pub name: String,
     pub model: i32,
+    pub owner: Option<i32>,
+    pub status: i16,
 }
 
 #[derive(Insertable)]
@@ -70,6 +81,8 @@ pub struct NewLabelSet<'a> {
     pub uuid: &'a str,
     pub name: &'a str,
     pub model: i32,
+    pub owner: Option<i32>,
+    pub status: i16,
 }
 
 #[derive(Queryable, Clone)]
@@ -79,6 +92,18 @@ pub struct Label {
     pub name: String,
     pub colour: String,
     pub vertices: Vec<u8>,
+    pub vertexcount: Option<i32>,
+    pub area: Option<f64>,
+    pub centroidx: Option<f64>,
+    pub centroidy: Option<f64>,
+    pub centroidz: Option<f64>,
+    pub minx: Option<f64>,
+    pub miny: Option<f64>,
+    pub minz: Option<f64>,
+    pub maxx: Option<f64>,
+    pub maxy: Option<f64>,
+    pub maxz: Option<f64>,
+    pub synonyms: Option<String>,
 }
 
 #[derive(Insertable)]
@@ -86,8 +111,20 @@ pub struct Label {
 pub struct NewLabel<'a> {
     pub labelset: i32,
     pub name: &'a str,
-    pub colour: &'a str,
+    pub colour: String,
     pub vertices: &'a [u8],
+    pub vertexcount: Option<i32>,
+    pub area: Option<f64>,
+    pub centroidx: Option<f64>,
+    pub centroidy: Option<f64>,
+    pub centroidz: Option<f64>,
+    pub minx: Option<f64>,
+    pub miny: Option<f64>,
+    pub minz: Option<f64>,
+    pub maxx: Option<f64>,
+    pub maxy: Option<f64>,
+    pub maxz: Option<f64>,
+    pub synonyms: Option<String>,
 }
 
 #[derive(Queryable, Clone, Insertable)]
@@ -104,13 +141,19 @@ pub struct UserQuiz {
     pub quiz: i32,
 }
 
-#[derive(Queryable)]
+#[derive(Queryable, Clone)]
 pub struct Quiz {
     pub id: i32,
     pub uuid: String,
     pub name: String,
     pub labelset: i32,
     pub shuffle: i16,
+    pub owner: Option<i32>,
+    pub opensat: Option<i64>,
+    pub closesat: Option<i64>,
+    pub timelimit: Option<i32>,
+    pub drawcount: Option<i32>,
+    pub status: i16,
 }
 
 #[derive(Insertable)]
@@ -121,6 +164,12 @@ pub struct NewQuiz<'a> {
     pub name: &'a str,
     pub labelset: i32,
     pub shuffle: i16,
+    pub owner: Option<i32>,
+    pub opensat: Option<i64>,
+    pub closesat: Option<i64>,
+    pub timelimit: Option<i32>,
+    pub drawcount: Option<i32>,
+    pub status: i16,
 }
 
 #[derive(Queryable, Debug)]
@@ -132,15 +181,216 @@ pub struct Question {
     pub textanswer: Option<String>,
     pub label: Option<i32>,
     pub showregions: i16,
+    pub position: i32,
+    pub acceptedanswers: Option<String>,
+    pub typotolerance: Option<i32>,
+    pub overlapmeasure: Option<i16>,
+    pub passthreshold: Option<f64>,
+    pub explanation: Option<String>,
+    pub highlightlabels: Option<String>,
 }
 
 #[derive(Insertable)]
 #[table_name = "questions"]
 pub struct NewQuestion<'a> {
+    pub id: Option<i32>,
     pub quiz: i32,
     pub questiontype: i16,
     pub textprompt: &'a str,
     pub textanswer: Option<&'a str>,
     pub label: Option<i32>,
     pub showregions: i16,
+    pub position: i32,
+    pub acceptedanswers: Option<String>,
+    pub typotolerance: Option<i32>,
+    pub overlapmeasure: Option<i16>,
+    pub passthreshold: Option<f64>,
+    pub explanation: Option<&'a str>,
+    pub highlightlabels: Option<String>,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct QuestionOption {
+    pub id: i32,
+    pub question: i32,
+    pub textoption: Option<String>,
+    pub label: Option<i32>,
+    pub correct: i16,
+}
+
+#[derive(Insertable)]
+#[table_name = "questionoptions"]
+pub struct NewQuestionOption<'a> {
+    pub question: i32,
+    pub textoption: Option<&'a str>,
+    pub label: Option<i32>,
+    pub correct: i16,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct QuestionHint {
+    pub id: i32,
+    pub question: i32,
+    pub texthint: String,
+    pub penalty: f64,
+}
+
+#[derive(Insertable)]
+#[table_name = "questionhints"]
+pub struct NewQuestionHint<'a> {
+    pub question: i32,
+    pub texthint: &'a str,
+    pub penalty: f64,
+}
+
+#[derive(Queryable, Debug)]
+pub struct Attempt {
+    pub id: i32,
+    pub uuid: String,
+    pub quiz: i32,
+    pub userid: Option<i32>,
+    pub startedat: i64,
+    pub deadline: Option<i64>,
+    pub submittedat: Option<i64>,
+    pub score: Option<f64>,
+    pub seed: i64,
+    pub nickname: Option<String>,
+    pub sharelink: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[table_name = "attempts"]
+pub struct NewAttempt<'a> {
+    pub uuid: &'a str,
+    pub quiz: i32,
+    pub userid: Option<i32>,
+    pub startedat: i64,
+    pub deadline: Option<i64>,
+    pub seed: i64,
+    pub nickname: Option<&'a str>,
+    pub sharelink: Option<i32>,
+}
+
+/// A question drawn for an attempt, at its position from 0.
+#[derive(Queryable, Insertable, Clone, Debug)]
+#[table_name = "attemptquestions"]
+pub struct AttemptQuestion {
+    pub attempt: i32,
+    pub position: i32,
+    pub question: i32,
+}
+
+#[derive(Queryable, Debug)]
+pub struct Answer {
+    pub id: i32,
+    pub attempt: i32,
+    pub question: i32,
+    pub textanswer: Option<String>,
+    pub label: Option<i32>,
+    pub choices: Option<String>,
+    pub score: f64,
+    pub responsetime: Option<f64>,
+    pub verdict: Option<i16>,
+    pub vertices: Option<String>,
+    pub hintsused: i32,
+}
+
+#[derive(Insertable)]
+#[table_name = "answers"]
+pub struct NewAnswer<'a> {
+    pub attempt: i32,
+    pub question: i32,
+    pub textanswer: Option<&'a str>,
+    pub label: Option<i32>,
+    pub choices: Option<&'a str>,
+    pub score: f64,
+    pub responsetime: Option<f64>,
+    pub verdict: Option<i16>,
+    pub vertices: Option<&'a str>,
+    pub hintsused: i32,
+}
+
+/// How many of the hints to a question have been revealed during an attempt.
+#[derive(Queryable, Insertable, Clone, Debug)]
+#[table_name = "revealedhints"]
+pub struct RevealedHint {
+    pub attempt: i32,
+    pub question: i32,
+    pub revealed: i32,
+}
+
+#[derive(Queryable, Insertable, Clone, Debug)]
+#[table_name = "reviews"]
+pub struct Review {
+    pub userid: i32,
+    pub labelset: i32,
+    pub label: String,
+    pub easiness: f64,
+    pub intervaldays: i32,
+    pub repetitions: i32,
+    pub due: i64,
+}
+
+#[derive(Queryable, Debug)]
+pub struct ShareLink {
+    pub id: i32,
+    pub token: String,
+    pub quiz: Option<i32>,
+    pub labelset: Option<i32>,
+    pub createdby: i32,
+    pub createdat: i64,
+    pub expiresat: Option<i64>,
+    pub allowguests: i16,
+}
+
+#[derive(Insertable)]
+#[table_name = "sharelinks"]
+pub struct NewShareLink<'a> {
+    pub token: &'a str,
+    pub quiz: Option<i32>,
+    pub labelset: Option<i32>,
+    pub createdby: i32,
+    pub createdat: i64,
+    pub expiresat: Option<i64>,
+    pub allowguests: i16,
+}
+
+#[derive(Queryable, Debug)]
+pub struct Media {
+    pub id: i32,
+    pub uuid: String,
+    pub filename: String,
+    pub contenttype: String,
+    pub size: i64,
+    pub owner: Option<i32>,
+    pub uploadedat: i64,
+}
+
+#[derive(Insertable)]
+#[table_name = "media"]
+pub struct NewMedia<'a> {
+    pub uuid: &'a str,
+    pub filename: &'a str,
+    pub contenttype: &'a str,
+    pub size: i64,
+    pub owner: Option<i32>,
+    pub uploadedat: i64,
+}
+
+#[derive(Queryable, Debug)]
+pub struct Attachment {
+    pub id: i32,
+    pub media: i32,
+    pub question: Option<i32>,
+    pub labelset: Option<i32>,
+    pub label: Option<String>,
+}
+
+#[derive(Insertable)]
+#[table_name = "attachments"]
+pub struct NewAttachment<'a> {
+    pub media: i32,
+    pub question: Option<i32>,
+    pub labelset: Option<i32>,
+    pub label: Option<&'a str>,
 }
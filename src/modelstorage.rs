@@ -1,29 +1,94 @@
-use crate::{authentication, models::NewModel, schema::models::dsl, MainDbConn};
+use crate::{
+    authentication,
+    models::{Model, NewModel},
+    schema::models::dsl,
+    search, util, MainDbConn,
+};
 use diesel::{ExpressionMethods, RunQueryDsl};
-use rocket::{get, put, Data};
+use rocket::{get, put, response::status, Data};
 use rocket_contrib::json::Json;
-use std::{error::Error, io::Read};
+use sodiumoxide::crypto::hash::sha256;
+use std::{
+    error::Error,
+    fmt::Write as _,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
 
 const MIB: u64 = 1024u64.pow(2);
 const UPLOAD_SIZE_LIMIT: u64 = 75 * MIB;
 
+/// The longest name a file may be uploaded as, in bytes.
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Names Windows reserves for devices, with or without an extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Either the size of the uploaded file, or why it wasn't accepted.
+pub type UploadResult = Result<Json<u64>, status::BadRequest<Json<String>>>;
+
+/// Either the model of the uploaded file, or why it wasn't accepted.
+pub type ModelResult = Result<Json<Model>, status::BadRequest<Json<String>>>;
+
+/// An uploaded file, stored under its checksum.
+pub struct StoredFile {
+    /// The name of the file in `MODELS_DIR`.
+    pub filename: String,
+    pub checksum: String,
+    pub size: u64,
+}
+
+/// Uploads a model, giving the model that was created for it. Identical files are only stored
+/// once, so uploading a file that already has a model gives that model instead.
 #[put("/upload/<filename>", data = "<data>")]
 pub fn upload(
     admin: authentication::Admin,
     conn: MainDbConn,
     filename: String,
     data: Data,
-) -> Result<Json<u64>, Box<dyn Error>> {
-    let written = store_file(admin, &filename, data)?;
-    let filename = &filename;
-    rocket_contrib::databases::diesel::insert_into(dsl::models)
+) -> Result<ModelResult, Box<dyn Error>> {
+    use diesel::QueryDsl;
+    let name = match check_name(&filename) {
+        Ok(name) => name,
+        Err(e) => return Ok(Err(bad_request(e))),
+    };
+    let stored = match store_file(admin, name, data)? {
+        Ok(stored) => stored,
+        Err(e) => return Ok(Err(bad_request(&e))),
+    };
+
+    let inserted = rocket_contrib::databases::diesel::insert_into(dsl::models)
         .values(&NewModel {
-            filename,
+            filename: &stored.filename,
+            name,
+            checksum: Some(&stored.checksum),
+            size: Some(stored.size as i64),
             ..Default::default()
         })
-        .execute(&*conn)?;
+        .execute(&*conn);
+    let is_new = match inserted {
+        Ok(_) => true,
+        // The file was uploaded before, possibly by a concurrent upload that got there first.
+        Err(diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::UniqueViolation,
+            _,
+        )) => false,
+        Err(e) => return Err(e.into()),
+    };
+
+    let model = dsl::models
+        .filter(dsl::filename.eq(&stored.filename))
+        .load::<Model>(&*conn)?
+        .pop()
+        .ok_or("Can't find model that was just stored.")?;
+    if is_new {
+        search::index_model(&*conn, &model)?;
+    }
 
-    Ok(Json(written))
+    Ok(Ok(Json(model)))
 }
 
 #[put("/upload/mtl/<id>/<filename>", data = "<data>")]
@@ -33,16 +98,23 @@ pub fn upload_material(
     id: i32,
     filename: String,
     data: Data,
-) -> Result<Json<u64>, Box<dyn Error>> {
+) -> Result<UploadResult, Box<dyn Error>> {
     use diesel::QueryDsl;
-    let written = store_file(admin, &filename, data)?;
+    let name = match check_name(&filename) {
+        Ok(name) => name,
+        Err(e) => return Ok(Err(bad_request(e))),
+    };
+    let stored = match store_file(admin, name, data)? {
+        Ok(stored) => stored,
+        Err(e) => return Ok(Err(bad_request(&e))),
+    };
 
     let target = dsl::models.filter(dsl::id.eq(&id));
     rocket_contrib::databases::diesel::update(target)
-        .set(dsl::material.eq(&filename))
+        .set(dsl::material.eq(&stored.filename))
         .execute(&*conn)?;
 
-    Ok(Json(written))
+    Ok(Ok(Json(stored.size)))
 }
 
 #[put("/upload/tex/<id>/<filename>", data = "<data>")]
@@ -52,42 +124,147 @@ pub fn upload_texture(
     id: i32,
     filename: String,
     data: Data,
-) -> Result<Json<u64>, Box<dyn Error>> {
+) -> Result<UploadResult, Box<dyn Error>> {
     use diesel::QueryDsl;
-    let written = store_file(admin, &filename, data)?;
+    let name = match check_name(&filename) {
+        Ok(name) => name,
+        Err(e) => return Ok(Err(bad_request(e))),
+    };
+    let stored = match store_file(admin, name, data)? {
+        Ok(stored) => stored,
+        Err(e) => return Ok(Err(bad_request(&e))),
+    };
 
     let target = dsl::models.filter(dsl::id.eq(&id));
     rocket_contrib::databases::diesel::update(target)
-        .set(dsl::texture.eq(&filename))
+        .set(dsl::texture.eq(&stored.filename))
         .execute(&*conn)?;
 
-    Ok(Json(written))
+    Ok(Ok(Json(stored.size)))
+}
+
+fn bad_request(message: &str) -> status::BadRequest<Json<String>> {
+    status::BadRequest(Some(Json(message.to_owned())))
+}
+
+/// Checks the name a file is uploaded as, giving it without surrounding whitespace. The name is
+/// only shown to users and never used as a path, but is still kept to something that could be.
+pub fn check_name(name: &str) -> Result<&str, &'static str> {
+    let name = name.trim();
+    let stem = name.split('.').next().unwrap_or_default();
+    if name.is_empty() {
+        Err("The file name can't be empty.")
+    } else if name.len() > MAX_NAME_LENGTH {
+        Err("The file name is too long.")
+    } else if name.contains(&['/', '\\'][..]) {
+        Err("The file name can't contain path separators.")
+    } else if name.chars().any(char::is_control) {
+        Err("The file name can't contain control characters.")
+    } else if name.starts_with('.')
+        || RESERVED_NAMES
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(stem.trim_end()))
+    {
+        Err("The file name is reserved.")
+    } else {
+        Ok(name)
+    }
 }
 
+/// Stores an uploaded file under its SHA-256, keeping the extension of `name` so that the file is
+/// served with the right type. Identical files are only stored once, and a stored file is never
+/// replaced. Files over the size limit aren't stored, and give why.
 pub fn store_file(
     _admin: authentication::Admin,
-    filename: &str,
+    name: &str,
     data: Data,
-) -> Result<u64, Box<dyn Error>> {
-    let mut data_dir = std::env::var("MODELS_DIR")
-        .map(std::path::PathBuf::from)
-        .unwrap();
-    data_dir.push(&filename);
-    let path = data_dir.to_str().ok_or("Invalid path")?;
+) -> Result<Result<StoredFile, String>, Box<dyn Error>> {
+    let data_dir = std::env::var("MODELS_DIR").map(PathBuf::from)?;
+
+    // The upload is hashed while it's written, so it needs somewhere to go before it's named.
+    // Rocket doesn't serve files starting with a dot.
+    let mut temporary = data_dir.clone();
+    temporary.push(format!(".upload-{}", util::create_uuid()));
+    let mut reader = HashingReader {
+        inner: data.open(),
+        state: sha256::State::new(),
+    };
+    let size = match write_limited(&mut reader, &temporary, UPLOAD_SIZE_LIMIT)? {
+        Some(size) => size,
+        None => {
+            let error = format!("The file is larger than {} MiB.", UPLOAD_SIZE_LIMIT / MIB);
+            return Ok(Err(error));
+        }
+    };
+
+    let mut checksum = String::with_capacity(2 * sha256::DIGESTBYTES);
+    for byte in reader.state.finalize().0.iter() {
+        write!(checksum, "{:02x}", byte)?;
+    }
+    let filename = match extension(name) {
+        Some(extension) => format!("{}.{}", checksum, extension),
+        None => checksum.clone(),
+    };
+
+    let mut path = data_dir;
+    path.push(&filename);
+    if path.exists() {
+        std::fs::remove_file(&temporary)?;
+    } else {
+        std::fs::rename(&temporary, &path)?;
+    }
+
+    Ok(Ok(StoredFile {
+        filename,
+        checksum,
+        size,
+    }))
+}
+
+/// The extension of a file name in lowercase, if it's one that's safe to keep.
+fn extension(name: &str) -> Option<String> {
+    let extension = Path::new(name).extension()?.to_str()?;
+    if extension.is_empty() || !extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some(extension.to_ascii_lowercase())
+}
+
+/// Hashes what's read through it.
+struct HashingReader<R> {
+    inner: R,
+    state: sha256::State,
+}
 
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.state.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+/// Streams uploaded data to a file, giving up once more than `limit` bytes have been written.
+/// Files that were too large aren't kept, and give `None`.
+pub fn write_limited<R: Read>(data: R, path: &Path, limit: u64) -> io::Result<Option<u64>> {
     let mut file = std::fs::File::create(path)?;
-    let mut stream = data.open().take(UPLOAD_SIZE_LIMIT);
+    let mut stream = data.take(limit + 1);
     let written = std::io::copy(&mut stream, &mut file)?;
+    if written > limit {
+        drop(file);
+        std::fs::remove_file(path)?;
+        return Ok(None);
+    }
 
-    Ok(written)
+    Ok(Some(written))
 }
 
 #[get("/")]
 pub fn list(
     _auth: &authentication::User,
     conn: MainDbConn,
-) -> Result<Json<Vec<crate::models::Model>>, Box<dyn Error>> {
-    let models = dsl::models.load::<crate::models::Model>(&*conn)?;
+) -> Result<Json<Vec<Model>>, Box<dyn Error>> {
+    let models = dsl::models.load::<Model>(&*conn)?;
     Ok(Json(models))
 }
 
@@ -96,13 +273,87 @@ pub fn lookup(
     _auth: &authentication::User,
     conn: MainDbConn,
     id: i32,
-) -> Result<Option<Json<crate::models::Model>>, Box<dyn Error>> {
+) -> Result<Option<Json<Model>>, Box<dyn Error>> {
     use diesel::query_dsl::filter_dsl::FindDsl;
-    let model = dsl::models
-        .find(&id)
-        .load::<crate::models::Model>(&*conn)?
-        .pop()
-        .map(Json);
+    let model = dsl::models.find(&id).load::<Model>(&*conn)?.pop().map(Json);
 
     Ok(model)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_trimmed() {
+        assert_eq!(check_name("  arm.obj \t"), Ok("arm.obj"));
+        assert_eq!(
+            check_name("Upper limb (left).obj"),
+            Ok("Upper limb (left).obj")
+        );
+    }
+
+    #[test]
+    fn empty_names_are_rejected() {
+        assert!(check_name("").is_err());
+        assert!(check_name("   ").is_err());
+    }
+
+    #[test]
+    fn dot_names_are_rejected() {
+        assert!(check_name(".").is_err());
+        assert!(check_name("..").is_err());
+        assert!(check_name(".hidden.obj").is_err());
+    }
+
+    #[test]
+    fn path_separators_are_rejected() {
+        assert!(check_name("a/b").is_err());
+        assert!(check_name("a\\b").is_err());
+        assert!(check_name("../arm.obj").is_err());
+        assert!(check_name("..\\arm.obj").is_err());
+    }
+
+    #[test]
+    fn reserved_names_are_rejected() {
+        assert!(check_name("CON.txt").is_err());
+        assert!(check_name(" nul .obj").is_err());
+        assert!(check_name("com1").is_err());
+        assert!(check_name("Lpt9.tar.gz").is_err());
+        assert_eq!(check_name("console.obj"), Ok("console.obj"));
+    }
+
+    #[test]
+    fn control_characters_are_rejected() {
+        assert!(check_name("arm\u{0}.obj").is_err());
+        assert!(check_name("arm\n.obj").is_err());
+        assert!(check_name("arm\u{7f}.obj").is_err());
+    }
+
+    #[test]
+    fn long_names_are_rejected() {
+        let longest = format!("{}.obj", "a".repeat(MAX_NAME_LENGTH - 4));
+        assert_eq!(check_name(&longest), Ok(longest.as_str()));
+        let too_long = format!("{}.obj", "a".repeat(MAX_NAME_LENGTH - 3));
+        assert_eq!(too_long.len(), 256);
+        assert!(check_name(&too_long).is_err());
+        // The limit is in bytes, not characters.
+        assert!(check_name(&"é".repeat(128)).is_err());
+    }
+
+    #[test]
+    fn extensions_are_kept_in_lowercase() {
+        assert_eq!(extension("arm.OBJ").as_deref(), Some("obj"));
+        assert_eq!(extension("arm.tar.gz").as_deref(), Some("gz"));
+        assert_eq!(extension("texture.jp2").as_deref(), Some("jp2"));
+    }
+
+    #[test]
+    fn unsafe_extensions_are_dropped() {
+        assert_eq!(extension("arm"), None);
+        assert_eq!(extension("arm."), None);
+        assert_eq!(extension("arm.o-bj"), None);
+        assert_eq!(extension("arm.obj "), None);
+        assert_eq!(extension("arm.öbj"), None);
+    }
+}
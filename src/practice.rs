@@ -0,0 +1,203 @@
+use crate::{
+    authentication, models, publication,
+    schema::{
+        labels::dsl as labels_dsl, labelsets::dsl as labelsets_dsl, reviews::dsl as reviews_dsl,
+    },
+    util, MainDbConn,
+};
+use diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl, SqliteConnection};
+use rocket::{get, post, response::status};
+use rocket_contrib::{json::Json, uuid::Uuid};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+};
+
+const DAY: i64 = 24 * 60 * 60;
+const INITIAL_EASINESS: f64 = 2.5;
+const MIN_EASINESS: f64 = 1.3;
+const MAX_QUALITY: u8 = 5;
+
+/// The next label to practise.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonPracticeItem {
+    pub label_id: i32,
+    pub name: String,
+    /// Whether the label hasn't been practised before.
+    pub new: bool,
+    pub due: Option<i64>,
+    /// How many labels in the set are due for review, including this one.
+    pub due_count: usize,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonPracticeAnswer {
+    pub label_set: uuid::Uuid,
+    pub label_id: i32,
+    /// How well the label was recalled, from 0 for not at all to 5 for perfectly.
+    pub quality: u8,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonReview {
+    pub label_id: i32,
+    pub name: String,
+    pub easiness: f64,
+    pub interval_days: i32,
+    pub repetitions: i32,
+    pub due: i64,
+}
+
+/// Either the rescheduled review, or what was wrong with the answer.
+pub type AnswerResult = Result<Json<JsonReview>, status::BadRequest<Json<String>>>;
+
+/// Schedules the next review of a label following SM-2, given how well it was recalled.
+fn schedule(review: &mut models::Review, quality: u8, now: i64) {
+    if quality < 3 {
+        review.repetitions = 0;
+        review.intervaldays = 1;
+    } else {
+        review.intervaldays = match review.repetitions {
+            0 => 1,
+            1 => 6,
+            _ => (f64::from(review.intervaldays) * review.easiness).round() as i32,
+        };
+        review.repetitions += 1;
+    }
+
+    let lapse = f64::from(MAX_QUALITY - quality);
+    review.easiness = (review.easiness + 0.1 - lapse * (0.08 + lapse * 0.02)).max(MIN_EASINESS);
+    review.due = now + i64::from(review.intervaldays) * DAY;
+}
+
+/// Picks the label to practise next: the one most overdue for review, or else one that hasn't
+/// been practised yet, or else the one due soonest.
+#[get("/<uuid>/next")]
+pub fn next(
+    auth: &authentication::User,
+    conn: MainDbConn,
+    uuid: Uuid,
+) -> Result<Option<Json<JsonPracticeItem>>, Box<dyn Error>> {
+    let label_set = match load_label_set(&*conn, &auth.0, &uuid.to_string())? {
+        Some(set) => set,
+        None => return Ok(None),
+    };
+    let labels = labels_dsl::labels
+        .filter(labels_dsl::labelset.eq(&label_set.id))
+        .order(labels_dsl::id)
+        .load::<models::Label>(&*conn)?;
+    let reviews: HashMap<String, models::Review> = reviews_dsl::reviews
+        .filter(reviews_dsl::userid.eq(&auth.0.id))
+        .filter(reviews_dsl::labelset.eq(&label_set.id))
+        .load::<models::Review>(&*conn)?
+        .into_iter()
+        .map(|r| (r.label.clone(), r))
+        .collect();
+
+    // Reviews are kept by name, so labels sharing a name are practised as one.
+    let mut seen = HashSet::new();
+    let labels: Vec<_> = labels
+        .into_iter()
+        .filter(|l| seen.insert(l.name.clone()))
+        .map(|l| {
+            let due = reviews.get(&l.name).map(|r| r.due);
+            (l, due)
+        })
+        .collect();
+
+    let now = util::now();
+    let due_count = labels
+        .iter()
+        .filter(|(_, due)| due.map_or(false, |due| due <= now))
+        .count();
+    let next = labels
+        .iter()
+        .filter(|(_, due)| due.map_or(false, |due| due <= now))
+        .min_by_key(|(_, due)| *due)
+        .or_else(|| labels.iter().find(|(_, due)| due.is_none()))
+        .or_else(|| labels.iter().min_by_key(|(_, due)| *due));
+
+    Ok(next.map(|(label, due)| {
+        Json(JsonPracticeItem {
+            label_id: label.id,
+            name: label.name.clone(),
+            new: due.is_none(),
+            due: *due,
+            due_count,
+        })
+    }))
+}
+
+/// Records how well a label was recalled, and schedules its next review.
+#[post("/answer", format = "json", data = "<answer>")]
+pub fn answer(
+    auth: &authentication::User,
+    conn: MainDbConn,
+    answer: Json<JsonPracticeAnswer>,
+) -> Result<Option<AnswerResult>, Box<dyn Error>> {
+    if answer.quality > MAX_QUALITY {
+        let error = format!("The quality must be from 0 to {}.", MAX_QUALITY);
+        return Ok(Some(Err(status::BadRequest(Some(Json(error))))));
+    }
+
+    let label_set = match load_label_set(&*conn, &auth.0, &answer.label_set.to_string())? {
+        Some(set) => set,
+        None => return Ok(None),
+    };
+    let label = labels_dsl::labels
+        .find(&answer.label_id)
+        .filter(labels_dsl::labelset.eq(&label_set.id))
+        .load::<models::Label>(&*conn)?
+        .pop();
+    let label = match label {
+        Some(l) => l,
+        None => return Ok(None),
+    };
+
+    let now = util::now();
+    let mut review = reviews_dsl::reviews
+        .find((&auth.0.id, &label_set.id, &label.name))
+        .load::<models::Review>(&*conn)?
+        .pop()
+        .unwrap_or_else(|| models::Review {
+            userid: auth.0.id,
+            labelset: label_set.id,
+            label: label.name.clone(),
+            easiness: INITIAL_EASINESS,
+            intervaldays: 0,
+            repetitions: 0,
+            due: now,
+        });
+    schedule(&mut review, answer.quality, now);
+
+    rocket_contrib::databases::diesel::replace_into(reviews_dsl::reviews)
+        .values(&review)
+        .execute(&*conn)?;
+
+    Ok(Some(Ok(Json(JsonReview {
+        label_id: label.id,
+        name: review.label,
+        easiness: review.easiness,
+        interval_days: review.intervaldays,
+        repetitions: review.repetitions,
+        due: review.due,
+    }))))
+}
+
+/// Loads a label set the user may practise.
+fn load_label_set(
+    conn: &SqliteConnection,
+    user: &models::User,
+    uuid: &str,
+) -> QueryResult<Option<models::LabelSet>> {
+    Ok(labelsets_dsl::labelsets
+        .filter(labelsets_dsl::uuid.eq(uuid))
+        .limit(1)
+        .load::<models::LabelSet>(conn)?
+        .pop()
+        .filter(|set| publication::is_visible(user, set.status)))
+}
@@ -0,0 +1,63 @@
+use crate::models;
+use rocket::FromFormValue;
+use serde::{Deserialize, Serialize};
+
+/// Whether a quiz or label set is shown to regular users. Drafts and archived items are only
+/// seen by moderators.
+#[derive(Serialize, Deserialize, FromFormValue, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Publication {
+    #[form(value = "draft")]
+    Draft,
+    #[form(value = "published")]
+    Published,
+    #[form(value = "archived")]
+    Archived,
+}
+
+impl Publication {
+    const DRAFT: i16 = 0;
+    const PUBLISHED: i16 = 1;
+    const ARCHIVED: i16 = 2;
+
+    pub fn to_db(self) -> i16 {
+        match self {
+            Self::Draft => Self::DRAFT,
+            Self::Published => Self::PUBLISHED,
+            Self::Archived => Self::ARCHIVED,
+        }
+    }
+
+    /// Unknown values are treated as drafts, so they're never shown by mistake.
+    pub fn from_db(status: i16) -> Self {
+        match status {
+            Self::PUBLISHED => Self::Published,
+            Self::ARCHIVED => Self::Archived,
+            _ => Self::Draft,
+        }
+    }
+}
+
+/// Moderators see everything; regular users only see what has been published.
+pub fn sees_unpublished(user: &models::User) -> bool {
+    user.privilege >= models::Privilege::Moderator as i32
+}
+
+pub fn is_visible(user: &models::User, status: i16) -> bool {
+    status == Publication::PUBLISHED || sees_unpublished(user)
+}
+
+/// The statuses a listing shows to the user, narrowed to `requested` if given.
+pub fn visible_statuses(user: &models::User, requested: Option<Publication>) -> Vec<i16> {
+    if !sees_unpublished(user) {
+        return vec![Publication::PUBLISHED];
+    }
+    match requested {
+        Some(status) => vec![status.to_db()],
+        None => vec![
+            Publication::DRAFT,
+            Publication::PUBLISHED,
+            Publication::ARCHIVED,
+        ],
+    }
+}
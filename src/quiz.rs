@@ -1,13 +1,28 @@
 use crate::{
-    authentication, models,
-    schema::{questions::dsl as questions_dsl, quizzes::dsl as quizzes_dsl},
-    util, MainDbConn,
+    analysis::{self, JsonItemAnalysis},
+    attempts, authentication,
+    grading::{self, OverlapMeasure},
+    listing::{JsonPage, ListQuery, SortOrder},
+    media::{self, JsonMedia},
+    models,
+    publication::{self, Publication},
+    schema::{
+        questionhints::dsl as hints_dsl, questionoptions::dsl as options_dsl,
+        questions::dsl as questions_dsl, quizzes::dsl as quizzes_dsl,
+    },
+    search, sharing, util, MainDbConn,
 };
-use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
-use rocket::{delete, get, post, put};
+use diesel::{
+    expression_methods::EscapeExpressionMethods, ExpressionMethods, QueryDsl, QueryResult,
+    RunQueryDsl, SqliteConnection, TextExpressionMethods,
+};
+use rocket::{delete, get, http::Status, post, put, request::Form, response::status};
 use rocket_contrib::{json::Json, uuid::Uuid};
 use serde::{Deserialize, Serialize};
-use std::error::Error;
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -15,21 +30,566 @@ pub struct JsonQuiz {
     pub id: Option<i32>,
     pub name: String,
     pub label_set: i32,
+    /// Whether each attempt gets the questions in its own order.
     pub shuffle: bool,
+    /// Each attempt gets this many questions drawn at random, rather than all of them.
+    pub draw_count: Option<i32>,
+    /// When the quiz can first be attempted, in seconds since the Unix epoch.
+    pub opens_at: Option<i64>,
+    /// When attempts can no longer be started or submitted, in seconds since the Unix epoch.
+    pub closes_at: Option<i64>,
+    /// How many seconds each attempt may take.
+    pub time_limit: Option<i32>,
+    /// Left out to keep the status of a saved quiz. New quizzes start out as drafts.
+    #[serde(default)]
+    pub status: Option<Publication>,
+    /// Seconds left of the user's ongoing attempt, or until the quiz closes if there is none.
+    #[serde(default, skip_deserializing)]
+    pub remaining_seconds: Option<i64>,
     pub questions: Vec<JsonQuestion>,
+    /// Why each stored question that couldn't be read was left out. Saving the quiz deletes them.
+    #[serde(default, skip_deserializing)]
+    pub skipped: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct JsonQuestion {
-    pub question_type: i16,
+    /// Kept when the quiz is saved, so that answers stay linked to their questions. New
+    /// questions are given one when saved.
+    #[serde(default)]
+    pub id: Option<i32>,
+    #[serde(flatten)]
+    pub kind: QuestionKind,
     pub text_prompt: String,
-    pub text_answer: Option<String>,
+    pub show_regions: Option<bool>,
+    /// Other written answers accepted as correct, besides the answer of the question.
+    #[serde(default)]
+    pub accepted_answers: Vec<String>,
+    /// How many typos a written answer may have. The default depends on the length of the
+    /// answer, see `grading::default_tolerance`.
+    #[serde(default)]
+    pub typo_tolerance: Option<i32>,
+    /// Shown once the question has been answered.
+    #[serde(default)]
+    pub explanation: Option<String>,
+    /// Revealed one at a time on request while answering, each at the cost of its penalty.
+    #[serde(default)]
+    pub hints: Vec<JsonHint>,
+    /// Labels to highlight along with the explanation.
+    #[serde(default)]
+    pub highlight_labels: Vec<i32>,
+    /// Images and audio shown with the question. Attached through `media::attach`.
+    #[serde(default, skip_deserializing)]
+    pub attachments: Vec<JsonMedia>,
+}
+
+/// The kinds of questions, each with the fields it requires. The kind is sent by name as
+/// `questionType`, but the numeric `questiontype` stored in the database is also accepted and is
+/// the index of the variant, so the variants must not be reordered.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "questionType", rename_all = "camelCase")]
+pub enum QuestionKind {
+    /// Highlights a label and asks for its name, which is the expected answer unless another
+    /// is given.
+    #[serde(rename_all = "camelCase")]
+    NameRegion {
+        label_id: i32,
+        #[serde(default)]
+        text_answer: Option<String>,
+    },
+    /// Asks for a label to be found on the model, either by picking it or, with an overlap
+    /// measure, by painting it. Painted regions get partial credit by their overlap with the
+    /// label, and full credit once it reaches the pass threshold.
+    #[serde(rename_all = "camelCase")]
+    LocateRegion {
+        label_id: i32,
+        #[serde(default)]
+        overlap: Option<OverlapMeasure>,
+        #[serde(default)]
+        pass_threshold: Option<f64>,
+    },
+    /// Asks for a written answer, optionally about a label.
+    #[serde(rename_all = "camelCase")]
+    FreeText {
+        text_answer: String,
+        #[serde(default)]
+        label_id: Option<i32>,
+    },
+    /// Asks to pick the correct options among several.
+    #[serde(rename_all = "camelCase")]
+    MultipleChoice {
+        options: Vec<JsonOption>,
+        /// When saving, adds this many incorrect options picked at random from the other labels
+        /// in the label set.
+        #[serde(default, skip_serializing)]
+        distractors: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonOption {
+    pub text: Option<String>,
     pub label_id: Option<i32>,
+    pub correct: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonHint {
+    pub text: String,
+    /// Taken off the score of the question when the hint is revealed, from 0 to 1.
+    #[serde(default)]
+    pub penalty: f64,
+}
+
+/// A quiz as delivered to those taking it. The questions come without their answers, which are
+/// graded on the server and only included once the quiz has closed.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonDeliveredQuiz {
+    pub id: Option<i32>,
+    pub name: String,
+    pub label_set: i32,
+    pub shuffle: bool,
+    pub draw_count: Option<i32>,
+    pub opens_at: Option<i64>,
+    pub closes_at: Option<i64>,
+    pub time_limit: Option<i32>,
+    pub remaining_seconds: Option<i64>,
+    pub questions: Vec<JsonDeliveredQuestion>,
+}
+
+/// A question with only what's needed to answer it.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonDeliveredQuestion {
+    pub id: Option<i32>,
+    #[serde(flatten)]
+    pub kind: DeliveredKind,
+    pub text_prompt: String,
     pub show_regions: Option<bool>,
+    pub explanation: Option<String>,
+    pub hints: Vec<JsonHint>,
+    pub highlight_labels: Vec<i32>,
+    pub attachments: Vec<JsonMedia>,
+    /// The answer, once the quiz has closed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub solution: Option<JsonSolution>,
+}
+
+/// The kinds of questions, without their answers.
+#[derive(Serialize, Debug)]
+#[serde(tag = "questionType", rename_all = "camelCase")]
+pub enum DeliveredKind {
+    #[serde(rename_all = "camelCase")]
+    NameRegion { label_id: i32 },
+    /// The label to find is left out, and is to be painted rather than picked if `paint` is set.
+    #[serde(rename_all = "camelCase")]
+    LocateRegion { paint: bool },
+    #[serde(rename_all = "camelCase")]
+    FreeText { label_id: Option<i32> },
+    #[serde(rename_all = "camelCase")]
+    MultipleChoice { options: Vec<JsonDeliveredOption> },
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonDeliveredOption {
+    pub text: Option<String>,
+    pub label_id: Option<i32>,
+}
+
+/// What was withheld from a delivered question.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonSolution {
+    #[serde(flatten)]
+    pub kind: QuestionKind,
+    pub accepted_answers: Vec<String>,
+}
+
+impl QuestionKind {
+    const NAME_REGION: i16 = 0;
+    const LOCATE_REGION: i16 = 1;
+    const FREE_TEXT: i16 = 2;
+    const MULTIPLE_CHOICE: i16 = 3;
+
+    pub fn question_type(&self) -> i16 {
+        match self {
+            Self::NameRegion { .. } => Self::NAME_REGION,
+            Self::LocateRegion { .. } => Self::LOCATE_REGION,
+            Self::FreeText { .. } => Self::FREE_TEXT,
+            Self::MultipleChoice { .. } => Self::MULTIPLE_CHOICE,
+        }
+    }
+
+    pub fn text_answer(&self) -> Option<&str> {
+        match self {
+            Self::NameRegion { text_answer, .. } => text_answer.as_deref(),
+            Self::LocateRegion { .. } | Self::MultipleChoice { .. } => None,
+            Self::FreeText { text_answer, .. } => Some(text_answer),
+        }
+    }
+
+    pub fn label_id(&self) -> Option<i32> {
+        match self {
+            Self::NameRegion { label_id, .. } | Self::LocateRegion { label_id, .. } => {
+                Some(*label_id)
+            }
+            Self::FreeText { label_id, .. } => *label_id,
+            Self::MultipleChoice { .. } => None,
+        }
+    }
+
+    /// How painted regions are scored, as stored.
+    pub fn region_scoring(&self) -> (Option<i16>, Option<f64>) {
+        match self {
+            Self::LocateRegion {
+                overlap,
+                pass_threshold,
+                ..
+            } => (overlap.map(OverlapMeasure::to_db), *pass_threshold),
+            _ => (None, None),
+        }
+    }
+
+    /// The kind without its answer.
+    pub fn to_delivered(&self) -> DeliveredKind {
+        match self {
+            Self::NameRegion { label_id, .. } => DeliveredKind::NameRegion {
+                label_id: *label_id,
+            },
+            Self::LocateRegion { overlap, .. } => DeliveredKind::LocateRegion {
+                paint: overlap.is_some(),
+            },
+            Self::FreeText { label_id, .. } => DeliveredKind::FreeText {
+                label_id: *label_id,
+            },
+            Self::MultipleChoice { options, .. } => DeliveredKind::MultipleChoice {
+                options: options
+                    .iter()
+                    .map(|o| JsonDeliveredOption {
+                        text: o.text.clone(),
+                        label_id: o.label_id,
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    /// Reads the kind from a stored question and its options, failing if it lacks a field the
+    /// kind requires.
+    pub fn from_db(
+        question: &models::Question,
+        options: &[models::QuestionOption],
+    ) -> Result<Self, String> {
+        let missing = |field| format!("Question {} is missing its {}.", question.id, field);
+        match question.questiontype {
+            Self::NAME_REGION => Ok(Self::NameRegion {
+                label_id: question.label.ok_or_else(|| missing("label"))?,
+                text_answer: question.textanswer.clone(),
+            }),
+            Self::LOCATE_REGION => Ok(Self::LocateRegion {
+                label_id: question.label.ok_or_else(|| missing("label"))?,
+                overlap: question.overlapmeasure.and_then(OverlapMeasure::from_db),
+                pass_threshold: question.passthreshold,
+            }),
+            Self::FREE_TEXT => Ok(Self::FreeText {
+                text_answer: question
+                    .textanswer
+                    .clone()
+                    .ok_or_else(|| missing("answer"))?,
+                label_id: question.label,
+            }),
+            Self::MULTIPLE_CHOICE => Ok(Self::MultipleChoice {
+                options: options.iter().map(From::from).collect(),
+                distractors: None,
+            }),
+            n => Err(format!("Question {} has unknown type {}.", question.id, n)),
+        }
+    }
+}
+
+impl JsonQuestion {
+    /// Checks the requirements that the structure of the question can't express.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let blank = |s: &str| s.trim().is_empty();
+        match &self.kind {
+            QuestionKind::NameRegion {
+                text_answer: Some(answer),
+                ..
+            } if blank(answer) => Err("the answer can't be blank"),
+            QuestionKind::LocateRegion { .. } if blank(&self.text_prompt) => {
+                Err("locate questions need a prompt")
+            }
+            QuestionKind::FreeText { text_answer, .. } if blank(text_answer) => {
+                Err("the answer can't be blank")
+            }
+            QuestionKind::FreeText { .. } if blank(&self.text_prompt) => {
+                Err("free text questions need a prompt")
+            }
+            QuestionKind::MultipleChoice { .. } if blank(&self.text_prompt) => {
+                Err("multiple choice questions need a prompt")
+            }
+            QuestionKind::MultipleChoice { options, .. } if !options.iter().any(|o| o.correct) => {
+                Err("multiple choice questions need a correct option")
+            }
+            QuestionKind::MultipleChoice { options, .. }
+                if options
+                    .iter()
+                    .any(|o| o.label_id.is_none() && o.text.as_deref().map_or(true, blank)) =>
+            {
+                Err("every option needs a text or a label")
+            }
+            QuestionKind::MultipleChoice {
+                options,
+                distractors,
+            } if options.len() + (distractors.unwrap_or(0) as usize) < 2 => {
+                Err("multiple choice questions need at least two options")
+            }
+            QuestionKind::LocateRegion {
+                overlap: None,
+                pass_threshold: Some(_),
+                ..
+            } => Err("the pass threshold needs an overlap measure"),
+            QuestionKind::LocateRegion {
+                pass_threshold: Some(threshold),
+                ..
+            } if !(*threshold > 0.0 && *threshold <= 1.0) => {
+                Err("the pass threshold must be above 0 and at most 1")
+            }
+            QuestionKind::LocateRegion { .. } | QuestionKind::MultipleChoice { .. }
+                if !self.accepted_answers.is_empty() || self.typo_tolerance.is_some() =>
+            {
+                Err("only questions answered in writing can accept other answers")
+            }
+            _ if self.typo_tolerance.map_or(false, |t| t < 0) => {
+                Err("the typo tolerance can't be negative")
+            }
+            _ if self.hints.iter().any(|h| blank(&h.text)) => Err("hints can't be blank"),
+            _ if self
+                .hints
+                .iter()
+                .any(|h| !(h.penalty >= 0.0 && h.penalty <= 1.0)) =>
+            {
+                Err("the penalty of a hint must be from 0 to 1")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl JsonQuestion {
+    /// What revealing the first `revealed` hints takes off the score, at most full marks.
+    pub fn hint_penalty(&self, revealed: usize) -> f64 {
+        let penalty: f64 = self.hints.iter().take(revealed).map(|h| h.penalty).sum();
+        penalty.min(1.0)
+    }
+
+    /// Puts the options of a multiple choice question in an order decided by `seed` and the
+    /// question, so that the correct ones can't be told by where they are. Answers give options
+    /// by index, so they must be graded against the question shuffled the same way.
+    pub fn shuffle_options(&mut self, seed: i64) {
+        if let QuestionKind::MultipleChoice { options, .. } = &mut self.kind {
+            util::seeded_shuffle(options, seed ^ i64::from(self.id.unwrap_or_default()));
+        }
+    }
+
+    /// The question as delivered to those answering it, with its answer if `reveal_answer` is
+    /// set.
+    pub fn deliver(self, reveal_answer: bool) -> JsonDeliveredQuestion {
+        JsonDeliveredQuestion {
+            id: self.id,
+            kind: self.kind.to_delivered(),
+            text_prompt: self.text_prompt,
+            show_regions: self.show_regions,
+            explanation: self.explanation,
+            hints: self.hints,
+            highlight_labels: self.highlight_labels,
+            attachments: self.attachments,
+            solution: if reveal_answer {
+                Some(JsonSolution {
+                    kind: self.kind,
+                    accepted_answers: self.accepted_answers,
+                })
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Leaves out what's only to be seen after answering, keeping the hints already revealed.
+    pub fn withhold_feedback(&mut self, revealed: usize) {
+        self.explanation = None;
+        self.hints.truncate(revealed);
+        self.highlight_labels.clear();
+    }
+}
+
+impl From<&models::QuestionOption> for JsonOption {
+    fn from(o: &models::QuestionOption) -> Self {
+        Self {
+            text: o.textoption.clone(),
+            label_id: o.label,
+            correct: o.correct != 0,
+        }
+    }
+}
+
+/// Either the UUID of the saved quiz, or what was wrong with it.
+pub type SaveResult = Result<Json<String>, status::BadRequest<Json<Vec<String>>>>;
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonQuizSummary {
+    pub id: i32,
+    pub uuid: String,
+    pub name: String,
+    pub label_set: i32,
+    pub owner: Option<i32>,
+    pub status: Publication,
+}
+
+impl From<models::Quiz> for JsonQuizSummary {
+    fn from(quiz: models::Quiz) -> Self {
+        Self {
+            id: quiz.id,
+            uuid: quiz.uuid,
+            name: quiz.name,
+            label_set: quiz.labelset,
+            owner: quiz.owner,
+            status: Publication::from_db(quiz.status),
+        }
+    }
+}
+
+/// Describes a quiz with one question per label, for `generate`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonQuizTemplate {
+    pub name: String,
+    pub label_set: uuid::Uuid,
+    pub question_type: GeneratedKind,
+    /// Only labels with names containing this text, ignoring case, get a question.
+    #[serde(default)]
+    pub label_filter: Option<String>,
+    /// The prompt of each question, with `{name}` replaced by the name of the label.
+    pub prompt: String,
+    #[serde(default)]
+    pub shuffle: bool,
+    /// Incorrect options added to multiple choice questions.
+    #[serde(default = "JsonQuizTemplate::default_distractors")]
+    pub distractors: u32,
+}
+
+/// The kinds of questions that can be made from a label alone. Free text and multiple choice
+/// questions are answered with the name of the label.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum GeneratedKind {
+    NameRegion,
+    LocateRegion,
+    FreeText,
+    MultipleChoice,
+}
+
+impl JsonQuizTemplate {
+    fn default_distractors() -> u32 {
+        3
+    }
+
+    /// Makes a question for each label passing the filter.
+    pub fn to_quiz(&self, label_set: i32, labels: &[models::Label]) -> JsonQuiz {
+        let filter = self.label_filter.as_ref().map(|f| f.to_lowercase());
+        let questions = labels
+            .iter()
+            .filter(|l| {
+                filter
+                    .as_ref()
+                    .map_or(true, |f| l.name.to_lowercase().contains(f))
+            })
+            .map(|l| JsonQuestion {
+                id: None,
+                kind: match self.question_type {
+                    GeneratedKind::NameRegion => QuestionKind::NameRegion {
+                        label_id: l.id,
+                        text_answer: None,
+                    },
+                    GeneratedKind::LocateRegion => QuestionKind::LocateRegion {
+                        label_id: l.id,
+                        overlap: None,
+                        pass_threshold: None,
+                    },
+                    GeneratedKind::FreeText => QuestionKind::FreeText {
+                        text_answer: l.name.clone(),
+                        label_id: Some(l.id),
+                    },
+                    GeneratedKind::MultipleChoice => QuestionKind::MultipleChoice {
+                        options: vec![JsonOption {
+                            text: Some(l.name.clone()),
+                            label_id: Some(l.id),
+                            correct: true,
+                        }],
+                        distractors: Some(self.distractors),
+                    },
+                },
+                text_prompt: self.prompt.replace("{name}", &l.name),
+                show_regions: None,
+                accepted_answers: Vec::new(),
+                typo_tolerance: None,
+                explanation: None,
+                hints: Vec::new(),
+                highlight_labels: Vec::new(),
+                attachments: Vec::new(),
+            })
+            .collect();
+
+        JsonQuiz {
+            id: None,
+            name: self.name.clone(),
+            label_set,
+            shuffle: self.shuffle,
+            draw_count: None,
+            opens_at: None,
+            closes_at: None,
+            time_limit: None,
+            status: None,
+            remaining_seconds: None,
+            questions,
+            skipped: Vec::new(),
+        }
+    }
 }
 
 impl JsonQuiz {
+    /// The quiz as delivered to those taking it, with the answers if `reveal_answers` is set.
+    /// Options come in a random order. Attempts shuffle them by their own seed instead, so that
+    /// they're graded in the order they were shown.
+    pub fn deliver(self, reveal_answers: bool) -> JsonDeliveredQuiz {
+        let seed = util::random_seed();
+        JsonDeliveredQuiz {
+            id: self.id,
+            name: self.name,
+            label_set: self.label_set,
+            shuffle: self.shuffle,
+            draw_count: self.draw_count,
+            opens_at: self.opens_at,
+            closes_at: self.closes_at,
+            time_limit: self.time_limit,
+            remaining_seconds: self.remaining_seconds,
+            questions: self
+                .questions
+                .into_iter()
+                .map(|mut q| {
+                    q.shuffle_options(seed);
+                    q.deliver(reveal_answers)
+                })
+                .collect(),
+        }
+    }
+
     pub fn to_db_quiz<'a>(&'a self, uuid: &'a str) -> models::NewQuiz<'a> {
         models::NewQuiz {
             id: if self.id.unwrap_or(0) == 0 {
@@ -40,68 +600,452 @@ impl JsonQuiz {
             labelset: self.label_set,
             shuffle: self.shuffle as i16,
             name: self.name.as_ref(),
+            owner: None,
+            opensat: self.opens_at,
+            closesat: self.closes_at,
+            timelimit: self.time_limit,
+            drawcount: self.draw_count,
+            status: self.status.unwrap_or(Publication::Draft).to_db(),
             uuid,
         }
     }
 
-    pub fn to_db_questions(&'_ self, quiz_id: i32) -> Vec<models::NewQuestion<'_>> {
+    /// The questions to insert, keeping the IDs found in `reusable_ids`.
+    pub fn to_db_questions(
+        &'_ self,
+        quiz_id: i32,
+        reusable_ids: &HashSet<i32>,
+    ) -> Vec<models::NewQuestion<'_>> {
+        let mut used_ids = HashSet::new();
+        self.questions
+            .iter()
+            .enumerate()
+            .map(|(position, q)| {
+                let (overlapmeasure, passthreshold) = q.kind.region_scoring();
+                models::NewQuestion {
+                    id: q
+                        .id
+                        .filter(|id| reusable_ids.contains(id) && used_ids.insert(*id)),
+                    quiz: quiz_id,
+                    questiontype: q.kind.question_type(),
+                    textprompt: q.text_prompt.as_ref(),
+                    textanswer: q.kind.text_answer(),
+                    label: q.kind.label_id(),
+                    showregions: q.show_regions.map(|_| 1).unwrap_or(0),
+                    position: position as i32,
+                    acceptedanswers: util::join_lines(&q.accepted_answers),
+                    typotolerance: q.typo_tolerance,
+                    overlapmeasure,
+                    passthreshold,
+                    explanation: q.explanation.as_deref(),
+                    highlightlabels: Some(
+                        q.highlight_labels
+                            .iter()
+                            .map(|l| l.to_string())
+                            .collect::<Vec<_>>()
+                            .join(","),
+                    )
+                    .filter(|labels| !labels.is_empty()),
+                }
+            })
+            .collect()
+    }
+
+    /// Options for the multiple choice questions, given the IDs of the questions in order.
+    pub fn to_db_options(&'_ self, question_ids: &[i32]) -> Vec<models::NewQuestionOption<'_>> {
+        self.questions
+            .iter()
+            .zip(question_ids)
+            .flat_map(|(q, &question)| {
+                let options: &[JsonOption] = match &q.kind {
+                    QuestionKind::MultipleChoice { options, .. } => options,
+                    _ => &[],
+                };
+                options.iter().map(move |o| models::NewQuestionOption {
+                    question,
+                    textoption: o.text.as_deref(),
+                    label: o.label_id,
+                    correct: o.correct as i16,
+                })
+            })
+            .collect()
+    }
+
+    /// Hints for the questions, given the IDs of the questions in order.
+    pub fn to_db_hints(&'_ self, question_ids: &[i32]) -> Vec<models::NewQuestionHint<'_>> {
         self.questions
             .iter()
-            .map(|q| models::NewQuestion {
-                quiz: quiz_id,
-                questiontype: q.question_type as i16,
-                textprompt: q.text_prompt.as_ref(),
-                textanswer: q.text_answer.as_deref(),
-                label: q.label_id,
-                showregions: q.show_regions.map(|_| 1).unwrap_or(0),
+            .zip(question_ids)
+            .flat_map(|(q, &question)| {
+                q.hints.iter().map(move |h| models::NewQuestionHint {
+                    question,
+                    texthint: h.text.as_ref(),
+                    penalty: h.penalty,
+                })
             })
             .collect()
     }
+
+    /// Adds the requested number of distractors to each multiple choice question, using labels
+    /// from the set that aren't already among its options. Labels are compared by name as well,
+    /// so that an option written as text isn't repeated as a wrong one, and labels sharing a
+    /// name are only used once. The options are then shuffled, as they're stored in order and the
+    /// correct ones would otherwise always come first.
+    pub fn generate_distractors(&mut self, labels: &[models::Label]) {
+        let names: HashMap<i32, String> = labels
+            .iter()
+            .map(|l| (l.id, grading::normalise(&l.name)))
+            .collect();
+        for question in self.questions.iter_mut() {
+            if let QuestionKind::MultipleChoice {
+                options,
+                distractors,
+            } = &mut question.kind
+            {
+                let count = distractors.take().unwrap_or(0) as usize;
+                let mut taken: HashSet<&str> = HashSet::new();
+                for option in options.iter() {
+                    if let Some(name) = option.label_id.and_then(|l| names.get(&l)) {
+                        taken.insert(name);
+                    }
+                }
+                let texts: Vec<String> = options
+                    .iter()
+                    .filter_map(|o| o.text.as_deref().map(grading::normalise))
+                    .collect();
+                taken.extend(texts.iter().map(String::as_str));
+                let candidates = labels
+                    .iter()
+                    .filter(|l| taken.insert(&names[&l.id]))
+                    .collect();
+                options.extend(util::random_sample(candidates, count).into_iter().map(|l| {
+                    JsonOption {
+                        text: Some(l.name.clone()),
+                        label_id: Some(l.id),
+                        correct: false,
+                    }
+                }));
+                if count > 0 {
+                    let all = std::mem::take(options);
+                    let len = all.len();
+                    *options = util::random_sample(all, len);
+                }
+            }
+        }
+    }
+
+    /// Validates every question, returning a description of each problem found. Labels referred
+    /// to by questions and options must belong to the quiz's label set, given as `labels`.
+    pub fn validate(&self, labels: &[models::Label]) -> Vec<String> {
+        let label_ids: HashSet<i32> = labels.iter().map(|l| l.id).collect();
+        let mut errors = Vec::new();
+        if let (Some(opens), Some(closes)) = (self.opens_at, self.closes_at) {
+            if closes <= opens {
+                errors.push("The quiz must close after it opens.".to_owned());
+            }
+        }
+        if self.time_limit.map_or(false, |limit| limit <= 0) {
+            errors.push("The time limit must be positive.".to_owned());
+        }
+        if self.draw_count.map_or(false, |count| count <= 0) {
+            errors.push("At least one question must be drawn.".to_owned());
+        }
+        for (i, q) in self.questions.iter().enumerate() {
+            if let Err(e) = q.validate() {
+                errors.push(format!("Question {}: {}.", i + 1, e));
+            }
+
+            let mut referenced: Vec<i32> = q.kind.label_id().into_iter().collect();
+            if let QuestionKind::MultipleChoice { options, .. } = &q.kind {
+                referenced.extend(options.iter().filter_map(|o| o.label_id));
+            }
+            referenced.extend(q.highlight_labels.iter().copied());
+            for label in referenced.into_iter().filter(|l| !label_ids.contains(l)) {
+                errors.push(format!(
+                    "Question {}: label {} isn't in the label set.",
+                    i + 1,
+                    label
+                ));
+            }
+        }
+        errors
+    }
 }
 
-impl From<(models::Quiz, Vec<models::Question>)> for JsonQuiz {
-    fn from((quiz, questions): (models::Quiz, Vec<models::Question>)) -> Self {
+/// Questions that can't be read, such as those of an unknown type or missing a field their type
+/// requires, are left out rather than keeping the rest of the quiz from loading. Why is kept in
+/// `skipped`, for moderators to see.
+impl
+    From<(
+        models::Quiz,
+        Vec<models::Question>,
+        Vec<models::QuestionOption>,
+        Vec<models::QuestionHint>,
+    )> for JsonQuiz
+{
+    fn from(
+        (quiz, questions, options, hints): (
+            models::Quiz,
+            Vec<models::Question>,
+            Vec<models::QuestionOption>,
+            Vec<models::QuestionHint>,
+        ),
+    ) -> Self {
+        let mut skipped = Vec::new();
+        let questions = questions
+            .into_iter()
+            .filter_map(|q| {
+                let options: Vec<_> = options
+                    .iter()
+                    .filter(|o| o.question == q.id)
+                    .cloned()
+                    .collect();
+                let question_hints = hints
+                    .iter()
+                    .filter(|h| h.question == q.id)
+                    .map(|h| JsonHint {
+                        text: h.texthint.clone(),
+                        penalty: h.penalty,
+                    })
+                    .collect();
+                let kind = match QuestionKind::from_db(&q, &options) {
+                    Ok(kind) => kind,
+                    Err(e) => {
+                        skipped.push(e);
+                        return None;
+                    }
+                };
+                Some(JsonQuestion {
+                    id: Some(q.id),
+                    kind,
+                    text_prompt: q.textprompt,
+                    show_regions: Some(q.showregions != 0),
+                    accepted_answers: util::split_lines(q.acceptedanswers.as_deref()),
+                    typo_tolerance: q.typotolerance,
+                    explanation: q.explanation,
+                    hints: question_hints,
+                    attachments: Vec::new(),
+                    highlight_labels: q
+                        .highlightlabels
+                        .map(|l| l.split(',').filter_map(|l| l.parse().ok()).collect())
+                        .unwrap_or_default(),
+                })
+            })
+            .collect();
+
         JsonQuiz {
             id: Some(quiz.id),
             label_set: quiz.labelset,
             name: quiz.name,
             shuffle: quiz.shuffle != 0,
-            questions: questions
-                .into_iter()
-                .map(|q| JsonQuestion {
-                    question_type: q.questiontype,
-                    text_prompt: q.textprompt,
-                    text_answer: q.textanswer,
-                    label_id: q.label,
-                    show_regions: Some(q.showregions != 0),
-                })
-                .collect(),
+            opens_at: quiz.opensat,
+            closes_at: quiz.closesat,
+            time_limit: quiz.timelimit,
+            draw_count: quiz.drawcount,
+            status: Some(Publication::from_db(quiz.status)),
+            remaining_seconds: None,
+            questions,
+            skipped,
         }
     }
 }
 
-#[get("/<uuid>")]
-pub fn load(
-    _auth: &authentication::User,
+#[get("/?<query..>")]
+pub fn list(
+    auth: &authentication::User,
+    conn: MainDbConn,
+    query: Form<ListQuery>,
+) -> Result<Json<JsonPage<JsonQuizSummary>>, Box<dyn Error>> {
+    use crate::schema::labelsets::dsl as labelsets_dsl;
+
+    // Quizzes only refer to models through their label set.
+    let labelset_ids = match query.model_ids(&*conn)? {
+        Some(model_ids) => Some(
+            labelsets_dsl::labelsets
+                .select(labelsets_dsl::id)
+                .filter(labelsets_dsl::model.eq_any(model_ids))
+                .load::<i32>(&*conn)?,
+        ),
+        None => None,
+    };
+    let name_pattern = query.name_pattern();
+    let statuses = publication::visible_statuses(&auth.0, query.status);
+
+    // Boxed queries can't be cloned, so the filters are applied once for counting and once more
+    // for fetching the page.
+    let filtered = || {
+        let mut quizzes = quizzes_dsl::quizzes
+            .filter(quizzes_dsl::status.eq_any(statuses.clone()))
+            .into_boxed();
+        if let Some(ids) = &labelset_ids {
+            quizzes = quizzes.filter(quizzes_dsl::labelset.eq_any(ids.clone()));
+        }
+        if let Some(pattern) = &name_pattern {
+            quizzes = quizzes.filter(quizzes_dsl::name.like(pattern.clone()).escape('\\'));
+        }
+        if let Some(owner) = query.owner {
+            quizzes = quizzes.filter(quizzes_dsl::owner.eq(owner));
+        }
+        quizzes
+    };
+
+    let total = filtered().count().get_result::<i64>(&*conn)?;
+    let quizzes = match query.sort() {
+        SortOrder::Name => filtered().order(quizzes_dsl::name.asc()),
+        SortOrder::NameDesc => filtered().order(quizzes_dsl::name.desc()),
+        SortOrder::Newest => filtered().order(quizzes_dsl::id.desc()),
+        SortOrder::Oldest => filtered().order(quizzes_dsl::id.asc()),
+    }
+    .limit(query.per_page())
+    .offset(query.offset())
+    .load::<crate::models::Quiz>(&*conn)?;
+
+    let items = quizzes.into_iter().map(From::from).collect();
+    Ok(Json(query.to_page(items, total)))
+}
+
+/// Loads a quiz with its answers, for editing.
+#[get("/<uuid>", rank = 1)]
+pub fn load_authoring(
+    auth: authentication::Moderator,
     conn: MainDbConn,
     uuid: Uuid,
 ) -> Result<Option<Json<JsonQuiz>>, Box<dyn Error>> {
+    let quiz = match find(&*conn, &uuid)? {
+        Some(q) => q,
+        None => return Ok(None),
+    };
+
+    let remaining_seconds = attempts::remaining_seconds(&*conn, &quiz, auth.0.id)?;
+    let mut quiz = from_db(&*conn, quiz)?;
+    quiz.remaining_seconds = remaining_seconds;
+    Ok(Some(Json(quiz)))
+}
+
+/// Loads a quiz to be taken, without its answers until it has closed.
+#[get("/<uuid>", rank = 2)]
+pub fn load(
+    auth: &authentication::User,
+    conn: MainDbConn,
+    uuid: Uuid,
+) -> Result<Option<Json<JsonDeliveredQuiz>>, Box<dyn Error>> {
+    let quiz = match find(&*conn, &uuid)? {
+        Some(q) if publication::is_visible(&auth.0, q.status) => q,
+        _ => return Ok(None),
+    };
+
+    // Explanations and hints are for those who have answered the quiz.
+    let show_feedback = attempts::has_submitted(&*conn, quiz.id, auth.0.id)?;
+    let remaining_seconds = attempts::remaining_seconds(&*conn, &quiz, auth.0.id)?;
+    let reveal_answers = answers_revealed(&quiz);
+    let mut quiz = from_db(&*conn, quiz)?;
+    quiz.remaining_seconds = remaining_seconds;
+    if !show_feedback {
+        for question in quiz.questions.iter_mut() {
+            question.withhold_feedback(0);
+        }
+    }
+    Ok(Some(Json(quiz.deliver(reveal_answers))))
+}
+
+fn find(conn: &SqliteConnection, uuid: &Uuid) -> QueryResult<Option<models::Quiz>> {
+    Ok(quizzes_dsl::quizzes
+        .filter(quizzes_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<models::Quiz>(conn)?
+        .pop())
+}
+
+/// Answers are only given to those taking a quiz once it has closed, as until then they could be
+/// passed on to others still taking it.
+pub fn answers_revealed(quiz: &models::Quiz) -> bool {
+    quiz.closesat
+        .map_or(false, |closes_at| closes_at <= util::now())
+}
+
+/// Either the item analysis, or `Forbidden` for moderators that don't own the quiz.
+pub type AnalysisResult = Result<Json<JsonItemAnalysis>, Status>;
+
+/// Reports how difficult and discriminating each question has been across the submitted
+/// attempts. Only the owner of the quiz and administrators may see it.
+#[get("/<uuid>/analysis")]
+pub fn analyse(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+) -> Result<Option<AnalysisResult>, Box<dyn Error>> {
+    use crate::schema::answers::dsl as answers_dsl;
+    use crate::schema::attempts::dsl as attempts_dsl;
+
     let quiz = quizzes_dsl::quizzes
         .filter(quizzes_dsl::uuid.eq(&uuid.to_string()))
         .limit(1)
         .load::<crate::models::Quiz>(&*conn)?
         .pop();
-
     let quiz = match quiz {
         Some(q) => q,
         None => return Ok(None),
     };
+    if !authentication::may_edit(&auth.0, quiz.owner) {
+        return Ok(Some(Err(Status::Forbidden)));
+    }
 
     let questions = questions_dsl::questions
         .filter(questions_dsl::quiz.eq(&quiz.id))
+        .order((questions_dsl::position, questions_dsl::id))
         .load::<crate::models::Question>(&*conn)?;
+    let attempt_ids: Vec<i32> = attempts_dsl::attempts
+        .select(attempts_dsl::id)
+        .filter(attempts_dsl::quiz.eq(&quiz.id))
+        .filter(attempts_dsl::submittedat.is_not_null())
+        .load(&*conn)?;
+    let responses: Vec<analysis::Response> = answers_dsl::answers
+        .filter(answers_dsl::attempt.eq_any(&attempt_ids))
+        .load::<crate::models::Answer>(&*conn)?
+        .into_iter()
+        .map(|a| analysis::Response {
+            attempt: a.attempt,
+            question: a.question,
+            score: a.score,
+            response_time: a.responsetime,
+        })
+        .collect();
+
+    let questions: Vec<(i32, &str)> = questions
+        .iter()
+        .map(|q| (q.id, q.textprompt.as_ref()))
+        .collect();
+    Ok(Some(Ok(Json(analysis::item_analysis(
+        &questions, &responses,
+    )))))
+}
+
+/// Loads the questions of a quiz, in order, along with their options.
+pub fn from_db(conn: &SqliteConnection, quiz: models::Quiz) -> Result<JsonQuiz, Box<dyn Error>> {
+    let questions = questions_dsl::questions
+        .filter(questions_dsl::quiz.eq(&quiz.id))
+        .order((questions_dsl::position, questions_dsl::id))
+        .load::<crate::models::Question>(conn)?;
+
+    let question_ids: Vec<i32> = questions.iter().map(|q| q.id).collect();
+    let options = options_dsl::questionoptions
+        .filter(options_dsl::question.eq_any(&question_ids))
+        .order(options_dsl::id)
+        .load::<crate::models::QuestionOption>(conn)?;
+    let hints = hints_dsl::questionhints
+        .filter(hints_dsl::question.eq_any(&question_ids))
+        .order(hints_dsl::id)
+        .load::<crate::models::QuestionHint>(conn)?;
+
+    let mut attachments = media::for_questions(conn, &question_ids)?;
 
-    Ok(Some(Json((quiz, questions).into())))
+    let mut quiz = JsonQuiz::from((quiz, questions, options, hints));
+    for question in quiz.questions.iter_mut() {
+        if let Some(media) = question.id.and_then(|id| attachments.remove(&id)) {
+            question.attachments = media;
+        }
+    }
+    Ok(quiz)
 }
 
 #[post("/", format = "json", data = "<data>")]
@@ -109,7 +1053,7 @@ pub fn create(
     auth: authentication::Moderator,
     conn: MainDbConn,
     data: Json<JsonQuiz>,
-) -> Result<Option<Json<String>>, Box<dyn Error>> {
+) -> Result<Option<SaveResult>, Box<dyn Error>> {
     let mut data = data.into_inner();
     data.id = None; // Prerequisite to avoid an "insert".
     add(auth, conn, util::create_uuid(), data)
@@ -121,16 +1065,110 @@ pub fn put(
     conn: MainDbConn,
     uuid: Uuid,
     data: Json<JsonQuiz>,
-) -> Result<Option<Json<String>>, Box<dyn Error>> {
+) -> Result<Option<SaveResult>, Box<dyn Error>> {
     add(auth, conn, uuid, data.into_inner())
 }
 
-pub fn add(
+/// Publishes, archives or returns a quiz to draft. Only the owner of the quiz may.
+#[put("/<uuid>/status", format = "json", data = "<status>")]
+pub fn set_status(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+    status: Json<Publication>,
+) -> Result<Option<Status>, Box<dyn Error>> {
+    let quiz = match find(&*conn, &uuid)? {
+        Some(q) => q,
+        None => return Ok(None),
+    };
+    if !authentication::may_edit(&auth.0, quiz.owner) {
+        return Ok(Some(Status::Forbidden));
+    }
+
+    rocket_contrib::databases::diesel::update(quizzes_dsl::quizzes.find(&quiz.id))
+        .set(quizzes_dsl::status.eq(status.to_db()))
+        .execute(&*conn)?;
+    Ok(Some(Status::Ok))
+}
+
+/// Reorders the questions of a quiz. The body must list the IDs of all its questions.
+#[put("/<uuid>/order", format = "json", data = "<order>")]
+pub fn reorder(
     _auth: authentication::Moderator,
     conn: MainDbConn,
     uuid: Uuid,
-    quiz: JsonQuiz,
-) -> Result<Option<Json<String>>, Box<dyn Error>> {
+    order: Json<Vec<i32>>,
+) -> Result<Option<Status>, Box<dyn Error>> {
+    let quiz = quizzes_dsl::quizzes
+        .filter(quizzes_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<crate::models::Quiz>(&*conn)?
+        .pop();
+    let quiz = match quiz {
+        Some(q) => q,
+        None => return Ok(None),
+    };
+
+    let mut current: Vec<i32> = questions_dsl::questions
+        .select(questions_dsl::id)
+        .filter(questions_dsl::quiz.eq(&quiz.id))
+        .load(&*conn)?;
+    let mut requested = order.clone();
+    current.sort_unstable();
+    requested.sort_unstable();
+    if current != requested {
+        return Ok(Some(Status::BadRequest));
+    }
+
+    for (position, id) in order.iter().enumerate() {
+        rocket_contrib::databases::diesel::update(questions_dsl::questions.find(id))
+            .set(questions_dsl::position.eq(position as i32))
+            .execute(&*conn)?;
+    }
+
+    Ok(Some(Status::Ok))
+}
+
+/// Creates a quiz with one question per label in a label set.
+#[post("/generate", format = "json", data = "<template>")]
+pub fn generate(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    template: Json<JsonQuizTemplate>,
+) -> Result<Option<SaveResult>, Box<dyn Error>> {
+    use crate::schema::labels::dsl as labels_dsl;
+    use crate::schema::labelsets::dsl as labelsets_dsl;
+
+    let label_set = labelsets_dsl::labelsets
+        .filter(labelsets_dsl::uuid.eq(&template.label_set.to_string()))
+        .limit(1)
+        .load::<crate::models::LabelSet>(&*conn)?
+        .pop();
+    let label_set = match label_set {
+        Some(set) => set,
+        None => return Ok(None),
+    };
+
+    let labels = labels_dsl::labels
+        .filter(labels_dsl::labelset.eq(&label_set.id))
+        .order(labels_dsl::id)
+        .load::<crate::models::Label>(&*conn)?;
+    let quiz = template.to_quiz(label_set.id, &labels);
+    if quiz.questions.is_empty() {
+        let error = "No labels match the filter.".to_owned();
+        return Ok(Some(Err(status::BadRequest(Some(Json(vec![error]))))));
+    }
+
+    add(auth, conn, util::create_uuid(), quiz)
+}
+
+pub fn add(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+    mut quiz: JsonQuiz,
+) -> Result<Option<SaveResult>, Box<dyn Error>> {
+    use crate::schema::labels::dsl as labels_dsl;
     use crate::schema::labelsets::dsl as labelset_dsl;
 
     let uuid = uuid.to_string();
@@ -144,25 +1182,44 @@ pub fn add(
         return Ok(None);
     }
 
-    // Check if there's a previous ID to overwrite..
-    let previous_id: Option<i32> = quiz.id.or_else(|| {
-        quizzes_dsl::quizzes
-            .filter(quizzes_dsl::uuid.eq(&uuid.to_string()))
-            .limit(1)
-            .load::<crate::models::Quiz>(&*conn)
-            .ok()
-            .map(|mut sets| sets.pop().map(|set| set.id))
-            .flatten()
-    });
+    let labels = labels_dsl::labels
+        .filter(labels_dsl::labelset.eq(&quiz.label_set))
+        .load::<crate::models::Label>(&*conn)?;
+
+    // Distractors are added first, so that questions left with too few options are caught.
+    quiz.generate_distractors(&labels);
+
+    let errors = quiz.validate(&labels);
+    if !errors.is_empty() {
+        return Ok(Some(Err(status::BadRequest(Some(Json(errors))))));
+    }
+
+    // Check if there's a previous ID to overwrite, and keep its owner if so.
+    let previous = quizzes_dsl::quizzes
+        .filter(quizzes_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<crate::models::Quiz>(&*conn)?
+        .pop();
+    let previous_id: Option<i32> = quiz.id.or_else(|| previous.as_ref().map(|q| q.id));
 
+    // Questions keep their IDs across saves, so that answers given to them stay linked.
+    let mut previous_questions = HashSet::new();
     if let Some(previous_id) = previous_id {
-        rocket_contrib::databases::diesel::delete(questions_dsl::questions)
+        previous_questions = questions_dsl::questions
+            .select(questions_dsl::id)
             .filter(questions_dsl::quiz.eq(&previous_id))
-            .execute(&*conn)?;
+            .load(&*conn)?
+            .into_iter()
+            .collect();
+        delete_questions(&conn, previous_id)?;
     }
 
     let mut dbquiz = quiz.to_db_quiz(&uuid);
     dbquiz.id = previous_id;
+    if let (None, Some(previous)) = (quiz.status, &previous) {
+        dbquiz.status = previous.status;
+    }
+    dbquiz.owner = previous.and_then(|q| q.owner).or(Some(auth.0.id));
     rocket_contrib::databases::diesel::replace_into(quizzes_dsl::quizzes)
         .values(&dbquiz)
         .execute(&*conn)?;
@@ -180,13 +1237,37 @@ pub fn add(
         })
         .ok_or("Can't find quiz that was just inserted.")?;
 
-    let questions = quiz.to_db_questions(previous_id);
+    let questions = quiz.to_db_questions(previous_id, &previous_questions);
 
     rocket_contrib::databases::diesel::insert_into(questions_dsl::questions)
         .values(&questions)
         .execute(&*conn)?;
 
-    Ok(Some(Json(uuid)))
+    let question_ids: Vec<i32> = questions_dsl::questions
+        .select(questions_dsl::id)
+        .filter(questions_dsl::quiz.eq(&previous_id))
+        .order(questions_dsl::position)
+        .load(&*conn)?;
+    rocket_contrib::databases::diesel::insert_into(options_dsl::questionoptions)
+        .values(&quiz.to_db_options(&question_ids))
+        .execute(&*conn)?;
+    rocket_contrib::databases::diesel::insert_into(hints_dsl::questionhints)
+        .values(&quiz.to_db_hints(&question_ids))
+        .execute(&*conn)?;
+    let removed: Vec<i32> = previous_questions
+        .into_iter()
+        .filter(|id| !question_ids.contains(id))
+        .collect();
+    media::delete_for_questions(&*conn, &removed)?;
+
+    search::index_quiz(
+        &*conn,
+        &uuid,
+        &quiz.name,
+        quiz.questions.iter().map(|q| q.text_prompt.as_ref()),
+    )?;
+
+    Ok(Some(Ok(Json(uuid))))
 }
 
 #[delete("/<uuid>")]
@@ -207,15 +1288,146 @@ pub fn delete(
         None => return Ok(None),
     };
 
+    let question_ids: Vec<i32> = questions_dsl::questions
+        .select(questions_dsl::id)
+        .filter(questions_dsl::quiz.eq(&quiz.id))
+        .load(&*conn)?;
+    media::delete_for_questions(&*conn, &question_ids)?;
+
     rocket_contrib::databases::diesel::delete(quizzes_dsl::quizzes)
         .filter(quizzes_dsl::uuid.eq(&uuid))
         .execute(&*conn)?;
-    rocket_contrib::databases::diesel::delete(questions_dsl::questions)
-        .filter(questions_dsl::quiz.eq(&quiz.id))
-        .execute(&*conn)?;
+    delete_questions(&conn, quiz.id)?;
+    attempts::delete_for_quiz(&*conn, quiz.id)?;
+    sharing::delete_for_quiz(&*conn, quiz.id)?;
     rocket_contrib::databases::diesel::delete(user_quizzes_dsl::userquizzes)
         .filter(user_quizzes_dsl::quiz.eq(&quiz.id))
         .execute(&*conn)?;
+    search::remove(&*conn, search::KIND_QUIZ, &uuid)?;
 
     Ok(Some(()))
 }
+
+/// Deletes the questions of a quiz along with their options and hints.
+fn delete_questions(conn: &MainDbConn, quiz_id: i32) -> Result<(), diesel::result::Error> {
+    let question_ids: Vec<i32> = questions_dsl::questions
+        .select(questions_dsl::id)
+        .filter(questions_dsl::quiz.eq(&quiz_id))
+        .load(&**conn)?;
+
+    rocket_contrib::databases::diesel::delete(options_dsl::questionoptions)
+        .filter(options_dsl::question.eq_any(&question_ids))
+        .execute(&**conn)?;
+    rocket_contrib::databases::diesel::delete(hints_dsl::questionhints)
+        .filter(hints_dsl::question.eq_any(&question_ids))
+        .execute(&**conn)?;
+    rocket_contrib::databases::diesel::delete(questions_dsl::questions)
+        .filter(questions_dsl::quiz.eq(&quiz_id))
+        .execute(&**conn)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(id: i32, name: &str) -> models::Label {
+        models::Label {
+            id,
+            labelset: 1,
+            name: name.to_owned(),
+            colour: "#ffffff".to_owned(),
+            vertices: Vec::new(),
+            vertexcount: None,
+            area: None,
+            centroidx: None,
+            centroidy: None,
+            centroidz: None,
+            minx: None,
+            miny: None,
+            minz: None,
+            maxx: None,
+            maxy: None,
+            maxz: None,
+            synonyms: None,
+        }
+    }
+
+    #[test]
+    fn shuffled_options_keep_their_answers() {
+        let question = || JsonQuestion {
+            id: Some(7),
+            kind: QuestionKind::MultipleChoice {
+                options: (0..8)
+                    .map(|i| JsonOption {
+                        text: None,
+                        label_id: Some(i),
+                        correct: i == 0,
+                    })
+                    .collect(),
+                distractors: None,
+            },
+            text_prompt: "Which nerve?".to_owned(),
+            show_regions: None,
+            accepted_answers: Vec::new(),
+            typo_tolerance: None,
+            explanation: None,
+            hints: Vec::new(),
+            highlight_labels: Vec::new(),
+            attachments: Vec::new(),
+        };
+        let shuffled = |seed| {
+            let mut question = question();
+            question.shuffle_options(seed);
+            match question.kind {
+                QuestionKind::MultipleChoice { options, .. } => options
+                    .iter()
+                    .map(|o| (o.label_id.unwrap(), o.correct))
+                    .collect::<Vec<_>>(),
+                _ => unreachable!(),
+            }
+        };
+
+        let options = shuffled(42);
+        assert_eq!(options, shuffled(42));
+        assert_eq!(options.len(), 8);
+        assert!(options.contains(&(0, true)));
+        assert_eq!(options.iter().filter(|(_, correct)| *correct).count(), 1);
+        assert!((0..20).any(|seed| shuffled(seed) != options));
+    }
+
+    #[test]
+    fn generated_multiple_choice_answers_vary_in_position() {
+        sodiumoxide::init().unwrap();
+        let labels: Vec<models::Label> =
+            (0..20).map(|i| label(i, &format!("Bone {}", i))).collect();
+        let template = JsonQuizTemplate {
+            name: "Bones".to_owned(),
+            label_set: uuid::Uuid::nil(),
+            question_type: GeneratedKind::MultipleChoice,
+            label_filter: None,
+            prompt: "Which is the {name}?".to_owned(),
+            shuffle: false,
+            distractors: 3,
+        };
+        let mut quiz = template.to_quiz(1, &labels);
+        quiz.generate_distractors(&labels);
+        assert!(quiz.validate(&labels).is_empty());
+
+        let positions: HashSet<usize> = quiz
+            .questions
+            .iter()
+            .map(|q| match &q.kind {
+                QuestionKind::MultipleChoice { options, .. } => {
+                    assert_eq!(options.len(), 4);
+                    assert_eq!(options.iter().filter(|o| o.correct).count(), 1);
+                    options.iter().position(|o| o.correct).unwrap()
+                }
+                kind => panic!("Expected a multiple choice question, got {:?}", kind),
+            })
+            .collect();
+        // All 20 answers landing in the same place has a chance of about 1 in 10^11.
+        assert!(positions.len() > 1, "answers always at {:?}", positions);
+    }
+}
@@ -1,3 +1,52 @@
+table! {
+    answers (id) {
+        id -> Integer,
+        attempt -> Integer,
+        question -> Integer,
+        textanswer -> Nullable<Text>,
+        label -> Nullable<Integer>,
+        choices -> Nullable<Text>,
+        score -> Double,
+        responsetime -> Nullable<Double>,
+        verdict -> Nullable<SmallInt>,
+        vertices -> Nullable<Text>,
+        hintsused -> Integer,
+    }
+}
+
+table! {
+    attachments (id) {
+        id -> Integer,
+        media -> Integer,
+        question -> Nullable<Integer>,
+        labelset -> Nullable<Integer>,
+        label -> Nullable<Text>,
+    }
+}
+table! {
+    attemptquestions (attempt, position) {
+        attempt -> Integer,
+        position -> Integer,
+        question -> Integer,
+    }
+}
+
+table! {
+    attempts (id) {
+        id -> Integer,
+        uuid -> Text,
+        quiz -> Integer,
+        userid -> Nullable<Integer>,
+        startedat -> BigInt,
+        deadline -> Nullable<BigInt>,
+        submittedat -> Nullable<BigInt>,
+        score -> Nullable<Double>,
+        seed -> BigInt,
+        nickname -> Nullable<Text>,
+        sharelink -> Nullable<Integer>,
+    }
+}
+
 table! {
     labels (id) {
         id -> Integer,
@@ -5,6 +54,18 @@ table! {
         name -> Text,
         colour -> Text,
         vertices -> Binary,
+        vertexcount -> Nullable<Integer>,
+        area -> Nullable<Double>,
+        centroidx -> Nullable<Double>,
+        centroidy -> Nullable<Double>,
+        centroidz -> Nullable<Double>,
+        minx -> Nullable<Double>,
+        miny -> Nullable<Double>,
+        minz -> Nullable<Double>,
+        maxx -> Nullable<Double>,
+        maxy -> Nullable<Double>,
+        maxz -> Nullable<Double>,
+        synonyms -> Nullable<Text>,
     }
 }
 
@@ -14,9 +75,22 @@ table! {
         uuid -> Text,
         name -> Text,
         model -> Integer,
+        owner -> Nullable<Integer>,
+        status -> SmallInt,
     }
 }
 
+table! {
+    media (id) {
+        id -> Integer,
+        uuid -> Text,
+        filename -> Text,
+        contenttype -> Text,
+        size -> BigInt,
+        owner -> Nullable<Integer>,
+        uploadedat -> BigInt,
+    }
+}
 table! {
     models (id) {
         id -> Integer,
@@ -24,6 +98,27 @@ table! {
         material -> Nullable<Text>,
         texture -> Nullable<Text>,
         category -> Nullable<Text>,
+        name -> Text,
+        checksum -> Nullable<Text>,
+        size -> Nullable<BigInt>,
+    }
+}
+
+table! {
+    questionhints (id) {
+        id -> Integer,
+        question -> Integer,
+        texthint -> Text,
+        penalty -> Double,
+    }
+}
+table! {
+    questionoptions (id) {
+        id -> Integer,
+        question -> Integer,
+        textoption -> Nullable<Text>,
+        label -> Nullable<Integer>,
+        correct -> SmallInt,
     }
 }
 
@@ -36,6 +131,13 @@ table! {
         textanswer -> Nullable<Text>,
         label -> Nullable<Integer>,
         showregions -> SmallInt,
+        position -> Integer,
+        acceptedanswers -> Nullable<Text>,
+        typotolerance -> Nullable<Integer>,
+        overlapmeasure -> Nullable<SmallInt>,
+        passthreshold -> Nullable<Double>,
+        explanation -> Nullable<Text>,
+        highlightlabels -> Nullable<Text>,
     }
 }
 
@@ -46,6 +148,44 @@ table! {
         name -> Text,
         labelset -> Integer,
         shuffle -> SmallInt,
+        owner -> Nullable<Integer>,
+        opensat -> Nullable<BigInt>,
+        closesat -> Nullable<BigInt>,
+        timelimit -> Nullable<Integer>,
+        drawcount -> Nullable<Integer>,
+        status -> SmallInt,
+    }
+}
+
+table! {
+    revealedhints (attempt, question) {
+        attempt -> Integer,
+        question -> Integer,
+        revealed -> Integer,
+    }
+}
+table! {
+    reviews (userid, labelset, label) {
+        userid -> Integer,
+        labelset -> Integer,
+        label -> Text,
+        easiness -> Double,
+        intervaldays -> Integer,
+        repetitions -> Integer,
+        due -> BigInt,
+    }
+}
+
+table! {
+    sharelinks (id) {
+        id -> Integer,
+        token -> Text,
+        quiz -> Nullable<Integer>,
+        labelset -> Nullable<Integer>,
+        createdby -> Integer,
+        createdat -> BigInt,
+        expiresat -> Nullable<BigInt>,
+        allowguests -> SmallInt,
     }
 }
 
@@ -73,11 +213,21 @@ table! {
 }
 
 allow_tables_to_appear_in_same_query!(
+    answers,
+    attachments,
+    attemptquestions,
+    attempts,
     labels,
     labelsets,
+    media,
     models,
+    questionhints,
+    questionoptions,
     questions,
     quizzes,
+    revealedhints,
+    reviews,
+    sharelinks,
     userlabelsets,
     userquizzes,
     users,
@@ -0,0 +1,167 @@
+//! Full-text search over labels, labelsets, quizzes and models.
+//!
+//! The `searchindex` table is an SQLite FTS5 virtual table that diesel's schema can't describe,
+//! so it is queried with raw SQL. Every write path that changes searchable text is responsible for
+//! calling the matching `index_*` or `remove` function here.
+
+use crate::{authentication, publication, MainDbConn};
+use diesel::{
+    sql_types::{Bool, Integer, SmallInt, Text},
+    RunQueryDsl, SqliteConnection,
+};
+use rocket::get;
+use rocket_contrib::json::Json;
+use serde::Serialize;
+use std::error::Error;
+
+const MAX_RESULTS: i32 = 50;
+
+pub const KIND_LABEL: &str = "label";
+pub const KIND_LABELSET: &str = "labelset";
+pub const KIND_QUIZ: &str = "quiz";
+pub const KIND_MODEL: &str = "model";
+
+#[derive(QueryableByName, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonSearchResult {
+    /// One of `label`, `labelset`, `quiz` or `model`.
+    #[sql_type = "Text"]
+    pub kind: String,
+    /// The UUID of the labelset or quiz, or the ID of the model. Labels refer to their labelset.
+    #[sql_type = "Text"]
+    pub target: String,
+    #[sql_type = "Text"]
+    pub name: String,
+    #[sql_type = "Text"]
+    pub snippet: String,
+}
+
+#[get("/?<q>")]
+pub fn search(
+    auth: &authentication::User,
+    conn: MainDbConn,
+    q: String,
+) -> Result<Json<Vec<JsonSearchResult>>, Box<dyn Error>> {
+    let query = match to_match_expression(&q) {
+        Some(query) => query,
+        None => return Ok(Json(Vec::new())),
+    };
+
+    // Matches in the name count much more than matches in the accompanying text. Drafts and
+    // archived items are indexed like any other, and left out here for regular users.
+    let published = publication::Publication::Published.to_db();
+    let results = diesel::sql_query(
+        "SELECT kind, target, name, snippet(searchindex, 3, '', '', '...', 12) AS snippet
+        FROM searchindex
+        WHERE searchindex MATCH ?
+            AND (? OR NOT (
+                (kind = 'quiz' AND target IN (SELECT uuid FROM quizzes WHERE status != ?))
+                OR (kind IN ('labelset', 'label')
+                    AND target IN (SELECT uuid FROM labelsets WHERE status != ?))
+            ))
+        ORDER BY bm25(searchindex, 0.0, 0.0, 10.0, 1.0)
+        LIMIT ?",
+    )
+    .bind::<Text, _>(query)
+    .bind::<Bool, _>(publication::sees_unpublished(&auth.0))
+    .bind::<SmallInt, _>(published)
+    .bind::<SmallInt, _>(published)
+    .bind::<Integer, _>(MAX_RESULTS)
+    .load::<JsonSearchResult>(&*conn)?;
+
+    Ok(Json(results))
+}
+
+/// Turns free text into an FTS5 query matching all words, with the last as a prefix so results
+/// show up while the user is still typing. Returns `None` if there is nothing to search for.
+fn to_match_expression(input: &str) -> Option<String> {
+    let words: Vec<_> = input
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| format!("\"{}\"", w))
+        .collect();
+
+    if words.is_empty() {
+        None
+    } else {
+        Some(format!("{}*", words.join(" ")))
+    }
+}
+
+/// Replaces the index entries of a labelset and its labels. Labels are given by name along with
+/// their synonyms, which are indexed with the name of the labelset.
+pub fn index_labelset<'a>(
+    conn: &SqliteConnection,
+    uuid: &str,
+    name: &str,
+    labels: impl IntoIterator<Item = (&'a str, &'a [String])>,
+) -> Result<(), diesel::result::Error> {
+    remove(conn, KIND_LABELSET, uuid)?;
+    remove(conn, KIND_LABEL, uuid)?;
+    insert(conn, KIND_LABELSET, uuid, name, "")?;
+    for (label, synonyms) in labels {
+        let body = std::iter::once(name)
+            .chain(synonyms.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        insert(conn, KIND_LABEL, uuid, label, &body)?;
+    }
+    Ok(())
+}
+
+/// Replaces the index entry of a quiz, which covers both its name and question prompts.
+pub fn index_quiz<'a>(
+    conn: &SqliteConnection,
+    uuid: &str,
+    name: &str,
+    prompts: impl IntoIterator<Item = &'a str>,
+) -> Result<(), diesel::result::Error> {
+    let body = prompts.into_iter().collect::<Vec<_>>().join(" ");
+    remove(conn, KIND_QUIZ, uuid)?;
+    insert(conn, KIND_QUIZ, uuid, name, &body)
+}
+
+/// Replaces the index entry of a model.
+pub fn index_model(
+    conn: &SqliteConnection,
+    model: &crate::models::Model,
+) -> Result<(), diesel::result::Error> {
+    let id = model.id.to_string();
+    remove(conn, KIND_MODEL, &id)?;
+    insert(
+        conn,
+        KIND_MODEL,
+        &id,
+        &model.name,
+        model.category.as_deref().unwrap_or_default(),
+    )
+}
+
+/// Removes every index entry of the given kind referring to `target`.
+pub fn remove(
+    conn: &SqliteConnection,
+    kind: &str,
+    target: &str,
+) -> Result<(), diesel::result::Error> {
+    diesel::sql_query("DELETE FROM searchindex WHERE kind = ? AND target = ?")
+        .bind::<Text, _>(kind)
+        .bind::<Text, _>(target)
+        .execute(conn)?;
+    Ok(())
+}
+
+fn insert(
+    conn: &SqliteConnection,
+    kind: &str,
+    target: &str,
+    name: &str,
+    body: &str,
+) -> Result<(), diesel::result::Error> {
+    diesel::sql_query("INSERT INTO searchindex (kind, target, name, body) VALUES (?, ?, ?, ?)")
+        .bind::<Text, _>(kind)
+        .bind::<Text, _>(target)
+        .bind::<Text, _>(name)
+        .bind::<Text, _>(body)
+        .execute(conn)?;
+    Ok(())
+}
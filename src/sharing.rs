@@ -0,0 +1,463 @@
+use crate::{
+    attempts::{self, AttemptResult, HintResult, JsonAnswer, JsonAttempt, Participant},
+    authentication,
+    labels::JsonLabelSet,
+    models,
+    quiz::{self, JsonDeliveredQuiz},
+    schema::{
+        attempts::dsl as attempts_dsl, labels::dsl as labels_dsl, labelsets::dsl as labelsets_dsl,
+        quizzes::dsl as quizzes_dsl, sharelinks::dsl as sharelinks_dsl,
+    },
+    util, MainDbConn,
+};
+use diesel::{ExpressionMethods, QueryDsl, QueryResult, RunQueryDsl, SqliteConnection};
+use rocket::{delete, get, http::Status, post, put};
+use rocket_contrib::{json::Json, uuid::Uuid};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+const MAX_NICKNAME_LENGTH: usize = 32;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonShareOptions {
+    /// When the link stops working, if ever.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// Whether visitors may attempt a shared quiz as guests.
+    #[serde(default)]
+    pub allow_guests: bool,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonShareLink {
+    pub token: String,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub allow_guests: bool,
+}
+
+impl From<models::ShareLink> for JsonShareLink {
+    fn from(link: models::ShareLink) -> Self {
+        Self {
+            token: link.token,
+            created_at: link.createdat,
+            expires_at: link.expiresat,
+            allow_guests: link.allowguests != 0,
+        }
+    }
+}
+
+/// What a share link gives access to. A shared quiz comes with its label set.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonShared {
+    pub quiz: Option<JsonDeliveredQuiz>,
+    pub label_set: JsonLabelSet,
+    pub expires_at: Option<i64>,
+    pub allow_guests: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonGuest {
+    pub nickname: String,
+}
+
+/// Either the link, or `Forbidden` for moderators that don't own what it shares.
+pub type ShareResult = Result<Json<JsonShareLink>, Status>;
+
+/// Either the links, or `Forbidden` for moderators that don't own what they share.
+pub type LinksResult = Result<Json<Vec<JsonShareLink>>, Status>;
+
+fn load_quiz(conn: &SqliteConnection, uuid: &Uuid) -> QueryResult<Option<models::Quiz>> {
+    Ok(quizzes_dsl::quizzes
+        .filter(quizzes_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<models::Quiz>(conn)?
+        .pop())
+}
+
+fn load_label_set(conn: &SqliteConnection, uuid: &Uuid) -> QueryResult<Option<models::LabelSet>> {
+    Ok(labelsets_dsl::labelsets
+        .filter(labelsets_dsl::uuid.eq(&uuid.to_string()))
+        .limit(1)
+        .load::<models::LabelSet>(conn)?
+        .pop())
+}
+
+/// Loads a share link, unless it has expired.
+fn load_link(conn: &SqliteConnection, token: &Uuid) -> QueryResult<Option<models::ShareLink>> {
+    let now = util::now();
+    Ok(sharelinks_dsl::sharelinks
+        .filter(sharelinks_dsl::token.eq(&token.to_string()))
+        .limit(1)
+        .load::<models::ShareLink>(conn)?
+        .pop()
+        .filter(|link| link.expiresat.map_or(true, |expires| now < expires)))
+}
+
+/// The owner of what a link shares.
+fn link_owner(conn: &SqliteConnection, link: &models::ShareLink) -> QueryResult<Option<i32>> {
+    Ok(match (link.quiz, link.labelset) {
+        (Some(quiz), _) => quizzes_dsl::quizzes
+            .find(&quiz)
+            .select(quizzes_dsl::owner)
+            .load::<Option<i32>>(conn)?
+            .pop()
+            .flatten(),
+        (None, Some(set)) => labelsets_dsl::labelsets
+            .find(&set)
+            .select(labelsets_dsl::owner)
+            .load::<Option<i32>>(conn)?
+            .pop()
+            .flatten(),
+        (None, None) => None,
+    })
+}
+
+fn create_link(
+    conn: &SqliteConnection,
+    user: &models::User,
+    quiz: Option<i32>,
+    labelset: Option<i32>,
+    options: &JsonShareOptions,
+) -> Result<JsonShareLink, Box<dyn Error>> {
+    let token = util::create_uuid().to_string();
+    rocket_contrib::databases::diesel::insert_into(sharelinks_dsl::sharelinks)
+        .values(&models::NewShareLink {
+            token: &token,
+            quiz,
+            labelset,
+            createdby: user.id,
+            createdat: util::now(),
+            expiresat: options.expires_at,
+            allowguests: (options.allow_guests && quiz.is_some()) as i16,
+        })
+        .execute(conn)?;
+
+    let link = sharelinks_dsl::sharelinks
+        .filter(sharelinks_dsl::token.eq(&token))
+        .limit(1)
+        .load::<models::ShareLink>(conn)?
+        .pop()
+        .ok_or("Can't find share link that was just inserted.")?;
+    Ok(link.into())
+}
+
+/// Creates a link giving anyone who has it read access to a quiz and its label set, and
+/// optionally letting them attempt the quiz as guests.
+#[post("/quiz/<uuid>", format = "json", data = "<options>")]
+pub fn share_quiz(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+    options: Json<JsonShareOptions>,
+) -> Result<Option<ShareResult>, Box<dyn Error>> {
+    let quiz = match load_quiz(&*conn, &uuid)? {
+        Some(q) => q,
+        None => return Ok(None),
+    };
+    if !authentication::may_edit(&auth.0, quiz.owner) {
+        return Ok(Some(Err(Status::Forbidden)));
+    }
+
+    let link = create_link(&*conn, &auth.0, Some(quiz.id), None, &options)?;
+    Ok(Some(Ok(Json(link))))
+}
+
+/// Creates a link giving anyone who has it read access to a label set.
+#[post("/labels/<uuid>", format = "json", data = "<options>")]
+pub fn share_label_set(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+    options: Json<JsonShareOptions>,
+) -> Result<Option<ShareResult>, Box<dyn Error>> {
+    let label_set = match load_label_set(&*conn, &uuid)? {
+        Some(set) => set,
+        None => return Ok(None),
+    };
+    if !authentication::may_edit(&auth.0, label_set.owner) {
+        return Ok(Some(Err(Status::Forbidden)));
+    }
+
+    let link = create_link(&*conn, &auth.0, None, Some(label_set.id), &options)?;
+    Ok(Some(Ok(Json(link))))
+}
+
+/// Lists the links sharing a quiz, including expired ones.
+#[get("/quiz/<uuid>")]
+pub fn quiz_links(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+) -> Result<Option<LinksResult>, Box<dyn Error>> {
+    let quiz = match load_quiz(&*conn, &uuid)? {
+        Some(q) => q,
+        None => return Ok(None),
+    };
+    if !authentication::may_edit(&auth.0, quiz.owner) {
+        return Ok(Some(Err(Status::Forbidden)));
+    }
+
+    let links = sharelinks_dsl::sharelinks
+        .filter(sharelinks_dsl::quiz.eq(&quiz.id))
+        .order(sharelinks_dsl::createdat)
+        .load::<models::ShareLink>(&*conn)?;
+    Ok(Some(Ok(Json(links.into_iter().map(From::from).collect()))))
+}
+
+/// Lists the links sharing a label set, including expired ones.
+#[get("/labels/<uuid>")]
+pub fn label_set_links(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    uuid: Uuid,
+) -> Result<Option<LinksResult>, Box<dyn Error>> {
+    let label_set = match load_label_set(&*conn, &uuid)? {
+        Some(set) => set,
+        None => return Ok(None),
+    };
+    if !authentication::may_edit(&auth.0, label_set.owner) {
+        return Ok(Some(Err(Status::Forbidden)));
+    }
+
+    let links = sharelinks_dsl::sharelinks
+        .filter(sharelinks_dsl::labelset.eq(&label_set.id))
+        .order(sharelinks_dsl::createdat)
+        .load::<models::ShareLink>(&*conn)?;
+    Ok(Some(Ok(Json(links.into_iter().map(From::from).collect()))))
+}
+
+/// Changes when a link expires and whether it lets guests attempt the quiz. Expired links can
+/// be brought back this way.
+#[put("/<token>", format = "json", data = "<options>")]
+pub fn update(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    token: Uuid,
+    options: Json<JsonShareOptions>,
+) -> Result<Option<ShareResult>, Box<dyn Error>> {
+    let link = sharelinks_dsl::sharelinks
+        .filter(sharelinks_dsl::token.eq(&token.to_string()))
+        .limit(1)
+        .load::<models::ShareLink>(&*conn)?
+        .pop();
+    let link = match link {
+        Some(l) => l,
+        None => return Ok(None),
+    };
+    if !authentication::may_edit(&auth.0, link_owner(&*conn, &link)?) {
+        return Ok(Some(Err(Status::Forbidden)));
+    }
+
+    let allow_guests = options.allow_guests && link.quiz.is_some();
+    rocket_contrib::databases::diesel::update(sharelinks_dsl::sharelinks.find(&link.id))
+        .set((
+            sharelinks_dsl::expiresat.eq(options.expires_at),
+            sharelinks_dsl::allowguests.eq(allow_guests as i16),
+        ))
+        .execute(&*conn)?;
+
+    Ok(Some(Ok(Json(JsonShareLink {
+        expires_at: options.expires_at,
+        allow_guests,
+        ..link.into()
+    }))))
+}
+
+/// Revokes a link. Guests that came through it can no longer load or submit their attempts.
+#[delete("/<token>")]
+pub fn revoke(
+    auth: authentication::Moderator,
+    conn: MainDbConn,
+    token: Uuid,
+) -> Result<Option<Status>, Box<dyn Error>> {
+    let link = sharelinks_dsl::sharelinks
+        .filter(sharelinks_dsl::token.eq(&token.to_string()))
+        .limit(1)
+        .load::<models::ShareLink>(&*conn)?
+        .pop();
+    let link = match link {
+        Some(l) => l,
+        None => return Ok(None),
+    };
+    if !authentication::may_edit(&auth.0, link_owner(&*conn, &link)?) {
+        return Ok(Some(Status::Forbidden));
+    }
+
+    rocket_contrib::databases::diesel::delete(sharelinks_dsl::sharelinks.find(&link.id))
+        .execute(&*conn)?;
+    Ok(Some(Status::Ok))
+}
+
+/// Loads what a link shares. Needs no account.
+#[get("/<token>")]
+pub fn open(conn: MainDbConn, token: Uuid) -> Result<Option<Json<JsonShared>>, Box<dyn Error>> {
+    let link = match load_link(&*conn, &token)? {
+        Some(l) => l,
+        None => return Ok(None),
+    };
+
+    let quiz = match link.quiz {
+        Some(id) => quizzes_dsl::quizzes
+            .find(&id)
+            .load::<models::Quiz>(&*conn)?
+            .pop()
+            .map(|q| {
+                let reveal_answers = quiz::answers_revealed(&q);
+                let mut quiz = quiz::from_db(&*conn, q)?;
+                // Visitors can't be told apart, so explanations and hints are withheld from all
+                // of them.
+                for question in quiz.questions.iter_mut() {
+                    question.withhold_feedback(0);
+                }
+                Ok::<_, Box<dyn Error>>(quiz.deliver(reveal_answers))
+            })
+            .transpose()?,
+        None => None,
+    };
+    let label_set_id = match (&quiz, link.labelset) {
+        (Some(quiz), _) => quiz.label_set,
+        (None, Some(set)) => set,
+        (None, None) => return Ok(None),
+    };
+    let label_set = match labelsets_dsl::labelsets
+        .find(&label_set_id)
+        .load::<models::LabelSet>(&*conn)?
+        .pop()
+    {
+        Some(set) => set,
+        None => return Ok(None),
+    };
+    let labels = labels_dsl::labels
+        .filter(labels_dsl::labelset.eq(&label_set.id))
+        .load::<models::Label>(&*conn)?;
+
+    Ok(Some(Json(JsonShared {
+        quiz,
+        label_set: JsonLabelSet::from_db(label_set, labels),
+        expires_at: link.expiresat,
+        allow_guests: link.allowguests != 0,
+    })))
+}
+
+/// Starts a guest attempt at a shared quiz, if the link allows it. Guests are known only by
+/// their nickname, and need the UUID of the attempt to get back to it.
+#[post("/<token>/attempts", format = "json", data = "<guest>")]
+pub fn start_attempt(
+    conn: MainDbConn,
+    token: Uuid,
+    guest: Json<JsonGuest>,
+) -> Result<Option<AttemptResult>, Box<dyn Error>> {
+    let link = match load_link(&*conn, &token)? {
+        Some(l) => l,
+        None => return Ok(None),
+    };
+    let quiz = match link.quiz {
+        Some(id) => quizzes_dsl::quizzes
+            .find(&id)
+            .load::<models::Quiz>(&*conn)?
+            .pop(),
+        None => None,
+    };
+    let quiz = match quiz {
+        Some(q) => q,
+        None => return Ok(None),
+    };
+
+    if link.allowguests == 0 {
+        let error = "This link doesn't let guests attempt the quiz.";
+        return Ok(Some(attempts::refused(Status::Forbidden, error)));
+    }
+    let nickname = guest.nickname.trim();
+    if nickname.is_empty() || nickname.chars().count() > MAX_NICKNAME_LENGTH {
+        let error = format!(
+            "The nickname must be from 1 to {} characters long.",
+            MAX_NICKNAME_LENGTH
+        );
+        return Ok(Some(attempts::refused(Status::BadRequest, &error)));
+    }
+
+    let participant = Participant::Guest {
+        nickname,
+        share_link: link.id,
+    };
+    attempts::start_attempt(&*conn, quiz, participant).map(Some)
+}
+
+#[get("/<token>/attempts/<uuid>")]
+pub fn load_attempt(
+    conn: MainDbConn,
+    token: Uuid,
+    uuid: Uuid,
+) -> Result<Option<Json<JsonAttempt>>, Box<dyn Error>> {
+    match load_guest_attempt(&*conn, &token, &uuid)? {
+        Some(attempt) => Ok(Some(Json(attempts::load_attempt(&*conn, attempt)?))),
+        None => Ok(None),
+    }
+}
+
+#[put("/<token>/attempts/<uuid>", format = "json", data = "<answers>")]
+pub fn submit_attempt(
+    conn: MainDbConn,
+    token: Uuid,
+    uuid: Uuid,
+    answers: Json<Vec<JsonAnswer>>,
+) -> Result<Option<AttemptResult>, Box<dyn Error>> {
+    match load_guest_attempt(&*conn, &token, &uuid)? {
+        Some(attempt) => attempts::submit_attempt(&*conn, attempt, answers.into_inner()).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Reveals the next hint to a question of a guest attempt.
+#[post("/<token>/attempts/<uuid>/hints/<question>")]
+pub fn reveal_hint(
+    conn: MainDbConn,
+    token: Uuid,
+    uuid: Uuid,
+    question: i32,
+) -> Result<Option<HintResult>, Box<dyn Error>> {
+    match load_guest_attempt(&*conn, &token, &uuid)? {
+        Some(attempt) => attempts::reveal_hint(&*conn, attempt, question),
+        None => Ok(None),
+    }
+}
+
+/// Loads a guest attempt made through a link, as long as the link still works.
+fn load_guest_attempt(
+    conn: &SqliteConnection,
+    token: &Uuid,
+    uuid: &Uuid,
+) -> QueryResult<Option<models::Attempt>> {
+    let link = match load_link(conn, token)? {
+        Some(l) => l,
+        None => return Ok(None),
+    };
+    Ok(attempts_dsl::attempts
+        .filter(attempts_dsl::uuid.eq(&uuid.to_string()))
+        .filter(attempts_dsl::userid.is_null())
+        .filter(attempts_dsl::sharelink.eq(&link.id))
+        .limit(1)
+        .load::<models::Attempt>(conn)?
+        .pop())
+}
+
+/// Deletes the links sharing a quiz.
+pub fn delete_for_quiz(conn: &SqliteConnection, quiz_id: i32) -> QueryResult<()> {
+    rocket_contrib::databases::diesel::delete(sharelinks_dsl::sharelinks)
+        .filter(sharelinks_dsl::quiz.eq(&quiz_id))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Deletes the links sharing a label set.
+pub fn delete_for_label_set(conn: &SqliteConnection, label_set_id: i32) -> QueryResult<()> {
+    rocket_contrib::databases::diesel::delete(sharelinks_dsl::sharelinks)
+        .filter(sharelinks_dsl::labelset.eq(&label_set_id))
+        .execute(conn)?;
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use crate::{
-    authentication, diesel::BoolExpressionMethods, models::UserLabelSet, schema, MainDbConn,
+    authentication, diesel::BoolExpressionMethods, models::UserLabelSet, publication, schema,
+    MainDbConn,
 };
 use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
 use rocket::{delete, get, put};
@@ -35,10 +36,10 @@ pub fn add(
         .limit(1)
         .load::<crate::models::LabelSet>(&*conn)?
         .pop();
-    if set.is_none() {
-        return Ok(None);
-    }
-    let set = set.unwrap();
+    let set = match set {
+        Some(s) if publication::is_visible(&user.0, s.status) => s,
+        _ => return Ok(None),
+    };
 
     let data = UserLabelSet {
         userid: user.0.id,
@@ -99,6 +100,7 @@ pub fn get(
 
     let result: Vec<_> = schema::labelsets::dsl::labelsets
         .filter(schema::labelsets::dsl::id.eq_any(&set_ids))
+        .filter(schema::labelsets::dsl::status.eq_any(publication::visible_statuses(&user.0, None)))
         .load::<crate::models::LabelSet>(&*conn)?
         .into_iter()
         .map(From::from)
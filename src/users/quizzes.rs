@@ -1,4 +1,7 @@
-use crate::{authentication, diesel::BoolExpressionMethods, models::UserQuiz, schema, MainDbConn};
+use crate::{
+    authentication, diesel::BoolExpressionMethods, models::UserQuiz, publication, schema,
+    MainDbConn,
+};
 use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
 use rocket::{delete, get, put};
 use rocket_contrib::{json::Json, uuid::Uuid};
@@ -33,10 +36,10 @@ pub fn add(
         .limit(1)
         .load::<crate::models::Quiz>(&*conn)?
         .pop();
-    if quiz.is_none() {
-        return Ok(None);
-    }
-    let set = quiz.unwrap();
+    let set = match quiz {
+        Some(s) if publication::is_visible(&user.0, s.status) => s,
+        _ => return Ok(None),
+    };
 
     let data = UserQuiz {
         userid: user.0.id,
@@ -97,6 +100,7 @@ pub fn get(
 
     let result: Vec<_> = schema::quizzes::dsl::quizzes
         .filter(schema::quizzes::dsl::id.eq_any(&quiz_ids))
+        .filter(schema::quizzes::dsl::status.eq_any(publication::visible_statuses(&user.0, None)))
         .load::<crate::models::Quiz>(&*conn)?
         .into_iter()
         .map(From::from)
@@ -1,4 +1,7 @@
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 pub fn create_uuid() -> rocket_contrib::uuid::Uuid {
     uuid::Uuid::new_v4()
@@ -10,3 +13,89 @@ pub fn create_uuid() -> rocket_contrib::uuid::Uuid {
 pub fn json_path(path: &str, file: &str) -> PathBuf {
     PathBuf::from(path).join(format!("{}.{}", file, "json"))
 }
+
+/// Picks up to `n` items at random, in random order.
+pub fn random_sample<T>(mut items: Vec<T>, n: usize) -> Vec<T> {
+    // A partial Fisher-Yates shuffle, moving the picked items to the front.
+    let n = n.min(items.len());
+    for i in 0..n {
+        let remaining = (items.len() - i) as u32;
+        let j = i + sodiumoxide::randombytes::randombytes_uniform(remaining) as usize;
+        items.swap(i, j);
+    }
+    items.truncate(n);
+    items
+}
+
+/// A random seed for `seeded_shuffle`.
+pub fn random_seed() -> i64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&sodiumoxide::randombytes::randombytes(8));
+    i64::from_le_bytes(bytes)
+}
+
+/// Shuffles the items into an order decided by `seed`, so that the same seed and items always
+/// give the same order.
+pub fn seeded_shuffle<T>(items: &mut [T], seed: i64) {
+    // SplitMix64, which is small and good enough for shuffling questions.
+    let mut state = seed as u64;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    for i in (1..items.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+/// The current time in seconds since the Unix epoch, which is how times are stored.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Escapes text for XML, dropping the control characters XML can't contain.
+pub fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Joins a list for storage in a single column, one item per line. Blank items are dropped, and
+/// an empty list is stored as null.
+pub fn join_lines(items: &[String]) -> Option<String> {
+    let items: Vec<String> = items
+        .iter()
+        .map(|item| item.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|item| !item.is_empty())
+        .collect();
+    if items.is_empty() {
+        None
+    } else {
+        Some(items.join("\n"))
+    }
+}
+
+/// Splits a list stored by `join_lines`.
+pub fn split_lines(stored: Option<&str>) -> Vec<String> {
+    stored
+        .map(|s| s.lines().map(str::to_owned).collect())
+        .unwrap_or_default()
+}